@@ -1,13 +1,79 @@
-use std::collections::{HashMap, BTreeSet};
+use std::{collections::{HashMap, BTreeSet}, sync::Arc};
 
 use type_uuid::TypeUuid;
 use uuid::Uuid;
 
-use crate::entity::EntityId;
+use crate::{component::IComponents, entity::EntityId, registry::{ComponentId, Signature}, sync::RwLock};
 
-#[derive(Debug, Clone, Default)]
+// плотный номер архетипа, индекс в списке архетипов мира
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ArchetypeId(u32);
+
+#[derive(Debug, Default)]
 pub struct Archetype {
+    id: ArchetypeId,
+    signature: Signature,
+    components: BTreeSet<Uuid>,
+    // своя колонка под своей блокировкой у каждого компонента архетипа:
+    // системы, пишущие один тип в непересекающихся архетипах, друг друга не ждут
+    columns: HashMap<Uuid, Arc<RwLock<dyn IComponents>>>,
+    // номера чанков строк архетипа в его собственных колонках
     chunk_ids: HashMap<Uuid, Vec<usize>>,
+    // строк в чанке, общая для всех колонок архетипа, иначе строки разных колонок разъедутся по чанкам
+    chunk_capacity: usize,
+    // переходы при добавлении и удалении компонента: целевой архетип по номеру компонента;
+    // архетипы из мира не удаляются, поэтому ребра не устаревают
+    add_edges: HashMap<ComponentId, ArchetypeId>,
+    remove_edges: HashMap<ComponentId, ArchetypeId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    Add,
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchetypeEdge {
+    pub kind: EdgeKind,
+    pub component: ComponentId,
+    pub target: ArchetypeId,
+}
+
+pub fn index(archetype_id: ArchetypeId) -> u32 {
+    archetype_id.0
+}
+
+pub fn from_index(index: u32) -> ArchetypeId {
+    ArchetypeId(index)
+}
+
+pub fn id(archetype: &Archetype) -> ArchetypeId {
+    archetype.id
+}
+
+pub fn signature(archetype: &Archetype) -> &Signature {
+    &archetype.signature
+}
+
+// uuid компонентов архетипа, включая EntityId
+pub fn components(archetype: &Archetype) -> &BTreeSet<Uuid> {
+    &archetype.components
+}
+
+pub fn chunk_capacity(archetype: &Archetype) -> usize {
+    archetype.chunk_capacity
+}
+
+pub fn column(archetype: &Archetype, component_uuid: Uuid) -> Option<&Arc<RwLock<dyn IComponents>>> {
+    archetype.columns.get(&component_uuid)
+}
+
+// колонка заводится при первой записи строки в архетип
+pub fn column_or_insert_with(archetype: &mut Archetype, component_uuid: Uuid, new: impl FnOnce() -> Arc<RwLock<dyn IComponents>>) -> Arc<RwLock<dyn IComponents>> {
+    archetype.columns.entry(component_uuid)
+        .or_insert_with(new)
+        .clone()
 }
 
 pub fn chunk_ids_by_type<TComponent: 'static + TypeUuid>(archetype: &Archetype) -> Option<&[usize]> {
@@ -19,6 +85,12 @@ pub fn chunk_ids(archetype: &Archetype, component_uuid: Uuid) -> Option<&[usize]
         .map(|x| x.as_ref())
 }
 
+pub fn chunk_position(archetype: &Archetype, component_uuid: Uuid, chunk_id: usize) -> Option<usize> {
+    archetype.chunk_ids.get(&component_uuid)?
+        .iter()
+        .position(|x| *x == chunk_id)
+}
+
 pub fn add_chunk_id(archetype: &mut Archetype, type_uuid: Uuid, chunk_id: usize) {
     archetype.chunk_ids.entry(type_uuid)
         .or_default()
@@ -29,23 +101,64 @@ pub fn has<TComponent: 'static + TypeUuid>(archetype: &Archetype) -> bool {
     archetype.chunk_ids.contains_key(&Uuid::from_bytes(TComponent::UUID))
 }
 
+pub fn has_component(archetype: &Archetype, component_uuid: Uuid) -> bool {
+    archetype.chunk_ids.contains_key(&component_uuid)
+}
+
+pub fn edge(archetype: &Archetype, kind: EdgeKind, component_id: ComponentId) -> Option<ArchetypeId> {
+    match kind {
+        EdgeKind::Add => archetype.add_edges.get(&component_id).copied(),
+        EdgeKind::Remove => archetype.remove_edges.get(&component_id).copied(),
+    }
+}
+
+pub fn set_edge(archetype: &mut Archetype, kind: EdgeKind, component_id: ComponentId, target: ArchetypeId) {
+    match kind {
+        EdgeKind::Add => archetype.add_edges.insert(component_id, target),
+        EdgeKind::Remove => archetype.remove_edges.insert(component_id, target),
+    };
+}
+
+// закешированные переходы архетипа для отладки, отсортированы по виду и номеру компонента
+pub fn edges(archetype: &Archetype) -> Vec<ArchetypeEdge> {
+    let add = archetype.add_edges.iter()
+        .map(|(component, target)| ArchetypeEdge { kind: EdgeKind::Add, component: *component, target: *target });
+
+    let remove = archetype.remove_edges.iter()
+        .map(|(component, target)| ArchetypeEdge { kind: EdgeKind::Remove, component: *component, target: *target });
+
+    let mut edges = add.chain(remove).collect::<Vec<_>>();
+
+    edges.sort_by_key(|edge| (edge.kind, edge.component));
+
+    edges
+}
+
+// архетип сущностей без компонентов: в нем есть только колонка EntityId
 pub fn is_empty(archetype: &Archetype) -> bool {
-    archetype.chunk_ids.len() == 0 &&
+    archetype.chunk_ids.len() == 1 &&
     archetype.chunk_ids.contains_key(&Uuid::from_bytes(EntityId::UUID))
 }
 
-pub fn new_empty() -> Archetype {
-    let mut archetype = Archetype::default();
-
-    archetype.chunk_ids.insert(Uuid::from_bytes(EntityId::UUID), Vec::new());
+pub fn empty_key() -> BTreeSet<Uuid> {
+    BTreeSet::from([Uuid::from_bytes(EntityId::UUID)])
+}
 
-    archetype
+pub fn new_empty(id: ArchetypeId, signature: Signature, chunk_capacity: usize) -> Archetype {
+    new(id, empty_key(), signature, chunk_capacity)
 }
 
-pub fn new(components_info: BTreeSet<Uuid>) -> Archetype {
+pub fn new(id: ArchetypeId, components: BTreeSet<Uuid>, signature: Signature, chunk_capacity: usize) -> Archetype {
     Archetype {
-        chunk_ids: HashMap::from_iter(components_info.into_iter()
-            .map(|id| (id, vec![]))
-        )
+        id,
+        signature,
+        chunk_ids: HashMap::from_iter(components.iter()
+            .map(|id| (*id, vec![]))
+        ),
+        components,
+        columns: HashMap::new(),
+        chunk_capacity,
+        add_edges: HashMap::new(),
+        remove_edges: HashMap::new(),
     }
-}
\ No newline at end of file
+}
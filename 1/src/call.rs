@@ -1,9 +1,6 @@
-use std::{any::TypeId, sync::Arc};
+use std::sync::Arc;
 
-use futures::{future::BoxFuture, Future};
-use tokio::sync::RwLock;
-
-use crate::{world::World, system::ISystem};
+use crate::{world::World, system::ISystem, commands, sync::RwLock};
 
 
 // pub async fn system<'system_call, TQuery, TQueryFut, TQueryResult, TPropsBuilder, TPropsBuilderFut, TProps, TSystem, TSystemFut>(world: &'system_call World, query: TQuery, props_builder: TPropsBuilder, system: TSystem)
@@ -31,11 +28,20 @@ use crate::{world::World, system::ISystem};
 // }
 
 pub async fn system<'system_call, TSystem: ISystem>(mut system: TSystem, world: Arc<RwLock<World>>) {
-    let world = world.read().await;
+    let mut commands = commands::new();
+
+    {
+        let world = world.read().await;
+
+        let Some(props) = system.query(&world).await else {
+            return;
+        };
 
-    let Some(props) = system.query(&world).await else {
-        return;
-    };
+        system.system(props, &world, &mut commands).await;
+    }
 
-    system.system(props, &world).await;
+    // точка синхронизации: система завершилась и отпустила мир, команды применяются под блокировкой на запись
+    if !commands::is_empty(&commands) {
+        commands::apply(&mut commands, &mut *world.write().await).await;
+    }
 }
\ No newline at end of file
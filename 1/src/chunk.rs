@@ -1,12 +1,46 @@
+use std::ops::Range;
 
 #[derive(Debug)]
 pub struct ComponentsChunk<TComponent> {
-    components: Vec<TComponent>
+    components: Vec<TComponent>,
+    // вместимость задается архетипом, а не Vec: у компонентов нулевого размера capacity у Vec бесконечная
+    capacity: usize,
+    ticks: ChunkTicks,
+}
+
+// размер чанка: фиксированное число строк или бюджет в байтах, из которого строки считаются по size_of компонента
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkSizing {
+    Rows(usize),
+    Bytes(usize),
+}
+
+impl Default for ChunkSizing {
+    fn default() -> Self {
+        ChunkSizing::Rows(32)
+    }
+}
+
+// в чанке всегда помещается хотя бы одна строка
+pub fn rows(sizing: ChunkSizing, component_size: usize) -> usize {
+    match sizing {
+        ChunkSizing::Rows(rows) => rows.max(1),
+        ChunkSizing::Bytes(bytes) => (bytes / component_size.max(1)).max(1),
+    }
+}
+
+// тик последнего добавления строки в чанк и тик последней выдачи чанка на запись
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkTicks {
+    pub added: u64,
+    pub changed: u64,
 }
 
 pub fn new_with_capacity<TComponent>(capacity: usize) -> ComponentsChunk<TComponent> {
     ComponentsChunk {
-        components: Vec::with_capacity(capacity)
+        components: Vec::with_capacity(capacity),
+        capacity,
+        ticks: ChunkTicks::default(),
     }
 }
 
@@ -18,11 +52,63 @@ pub fn components_mut<TComponent>(chunk: &mut ComponentsChunk<TComponent>) -> &m
     &mut chunk.components
 }
 
+pub fn ticks<TComponent>(chunk: &ComponentsChunk<TComponent>) -> ChunkTicks {
+    chunk.ticks
+}
+
+pub fn mark_changed<TComponent>(chunk: &mut ComponentsChunk<TComponent>, change_tick: u64) {
+    chunk.ticks.changed = chunk.ticks.changed.max(change_tick);
+}
+
+pub fn len<TComponent>(chunk: &ComponentsChunk<TComponent>) -> usize {
+    chunk.components.len()
+}
+
+pub fn is_empty<TComponent>(chunk: &ComponentsChunk<TComponent>) -> bool {
+    chunk.components.is_empty()
+}
+
+pub fn capacity<TComponent>(chunk: &ComponentsChunk<TComponent>) -> usize {
+    chunk.capacity
+}
+
 pub fn is_full_filled<TComponent>(chunk: &ComponentsChunk<TComponent>) -> bool {
-    chunk.components.capacity() <= chunk.components.len()
+    chunk.capacity <= chunk.components.len()
+}
+
+pub fn push<TComponent>(chunk: &mut ComponentsChunk<TComponent>, component: TComponent, change_tick: u64) -> usize {
+    push_with_ticks(chunk, component, ChunkTicks { added: change_tick, changed: change_tick })
 }
 
-pub fn push<TComponent>(chunk: &mut ComponentsChunk<TComponent>, component: TComponent) -> usize {
+// строка переезжает из другого чанка вместе с его тиками, чтобы Added и Changed не срабатывали из-за самого переезда
+pub fn push_with_ticks<TComponent>(chunk: &mut ComponentsChunk<TComponent>, component: TComponent, ticks: ChunkTicks) -> usize {
+    chunk.ticks.added = chunk.ticks.added.max(ticks.added);
+    mark_changed(chunk, ticks.changed);
+
     chunk.components.push(component);
     chunk.components.len() - 1
-}
\ No newline at end of file
+}
+
+// дописывает компоненты, пока чанк не заполнится; возвращает строки, которые заняли новые компоненты
+pub fn extend<TComponent>(chunk: &mut ComponentsChunk<TComponent>, components: &mut impl Iterator<Item = TComponent>, change_tick: u64) -> Range<usize> {
+    let start = chunk.components.len();
+    let free = chunk.capacity.saturating_sub(start);
+
+    chunk.components.extend(components.take(free));
+
+    if chunk.components.len() > start {
+        chunk.ticks.added = chunk.ticks.added.max(change_tick);
+        mark_changed(chunk, change_tick);
+    }
+
+    start..chunk.components.len()
+}
+
+pub fn pop<TComponent>(chunk: &mut ComponentsChunk<TComponent>) -> Option<TComponent> {
+    chunk.components.pop()
+}
+
+pub fn replace<TComponent>(chunk: &mut ComponentsChunk<TComponent>, component_idx: usize, component: TComponent) -> Option<TComponent> {
+    let slot = chunk.components.get_mut(component_idx)?;
+    Some(std::mem::replace(slot, component))
+}
@@ -0,0 +1,86 @@
+use std::fmt::Debug;
+
+use futures::{future::BoxFuture, FutureExt};
+use type_uuid::TypeUuid;
+
+use crate::{world::{World, self}, entity::{EntityId, self}, unknown_component::IntoComponentsInfo};
+
+type Command = Box<dyn for<'world> FnOnce(&'world mut World) -> BoxFuture<'world, ()> + Send>;
+
+// структурные изменения, записанные системой; мир меняется только в точке синхронизации, под блокировкой на запись
+#[derive(Default)]
+pub struct Commands {
+    commands: Vec<Command>,
+}
+
+impl Debug for Commands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Commands")
+            .field("len", &self.commands.len())
+            .finish()
+    }
+}
+
+pub fn new() -> Commands {
+    Commands::default()
+}
+
+pub fn len(commands: &Commands) -> usize {
+    commands.commands.len()
+}
+
+pub fn is_empty(commands: &Commands) -> bool {
+    commands.commands.is_empty()
+}
+
+// идентификатор резервируется в мире сразу, чтобы следующие команды этого же буфера могли ссылаться на сущность;
+// если буфер бросят, не применив, слот вернется в мир
+pub fn spawn<TComponents>(commands: &mut Commands, world: &World, components: TComponents) -> EntityId
+where
+    TComponents: IntoComponentsInfo + Send + 'static,
+{
+    let reservation = world::reservation(world);
+    let entity_id = entity::reservation_id(&reservation);
+
+    commands.commands.push(Box::new(move |world| async move {
+        world::add_entity_with_id(world, entity::fulfill(reservation), components).await;
+    }.boxed()));
+
+    entity_id
+}
+
+// редактор резервирует идентификатор сразу, а компоненты навешивает следующими командами
+pub fn spawn_empty(commands: &mut Commands, world: &World) -> EntityId {
+    spawn(commands, world, ())
+}
+
+pub fn despawn(commands: &mut Commands, entity_id: EntityId) {
+    commands.commands.push(Box::new(move |world| async move {
+        world::remove_entity(world, entity_id).await;
+    }.boxed()));
+}
+
+pub fn insert<TComponent>(commands: &mut Commands, entity_id: EntityId, component: TComponent)
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    commands.commands.push(Box::new(move |world| async move {
+        world::insert_component(world, entity_id, component).await;
+    }.boxed()));
+}
+
+pub fn remove<TComponent>(commands: &mut Commands, entity_id: EntityId)
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    commands.commands.push(Box::new(move |world| async move {
+        world::remove_component::<TComponent>(world, entity_id).await;
+    }.boxed()));
+}
+
+// команды применяются в порядке записи, команды для уже удаленных сущностей ничего не делают
+pub async fn apply(commands: &mut Commands, world: &mut World) {
+    for command in commands.commands.drain(..) {
+        command(world).await;
+    }
+}
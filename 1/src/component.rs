@@ -1,26 +1,76 @@
-use std::{collections::HashSet, any::{Any, type_name}, fmt::Debug};
+use std::{collections::HashSet, any::{Any, type_name}, fmt::Debug, ops::Range};
 
 use type_uuid::TypeUuid;
 
-use crate::{chunk::{ComponentsChunk, self}, type_info::TypeInfo};
+use crate::{chunk::{ComponentsChunk, ChunkTicks, self}, type_info::TypeInfo, unknown_component::IUknownComponent};
 
 #[derive(Debug)]
 pub struct Components<TComponent> where TComponent: Sync + Send + TypeUuid + Debug {
     chunks: Vec<ComponentsChunk<TComponent>>,
+    // тик, которым помечаются чанки, выданные на запись под текущей блокировкой
+    change_tick: u64,
 }
 
 pub fn new<TComponent: Sync + Send + TypeUuid + Debug>() -> Components<TComponent> {
     Components::<TComponent> {
-        chunks: Vec::with_capacity(32),
+        chunks: Vec::new(),
+        change_tick: 0,
     }
 }
 
+pub fn change_tick<TComponent: Sync + Send + TypeUuid + Debug>(components: &Components<TComponent>) -> u64 {
+    components.change_tick
+}
+
 pub fn chunk<TComponent: Sync + Send + TypeUuid + Debug>(components: &Components<TComponent>, chunk_id: usize) -> Option<&ComponentsChunk<TComponent>> {
     components.chunks.get(chunk_id)
 }
 
 pub fn chunk_mut<TComponent: Sync + Send + TypeUuid + Debug>(components: &mut Components<TComponent>, chunk_id: usize) -> Option<&mut ComponentsChunk<TComponent>> {
-    components.chunks.get_mut(chunk_id)
+    let chunk = components.chunks.get_mut(chunk_id)?;
+
+    chunk::mark_changed(chunk, components.change_tick);
+
+    Some(chunk)
+}
+
+// чанки не помечаются измененными, это делает доступ &mut T при выдаче строк, поэтому наружу крейта функция не выходит
+pub(crate) fn chunks_mut<TComponent: Sync + Send + TypeUuid + Debug>(components: &mut Components<TComponent>) -> &mut [ComponentsChunk<TComponent>] {
+    &mut components.chunks
+}
+
+// дописывает строки в чанки архетипа по порядку, недостающие чанки создаются одним резервированием;
+// возвращает для каждого затронутого чанка его индекс и занятые строки
+pub fn extend<TComponent: Sync + Send + TypeUuid + Debug>(components: &mut Components<TComponent>, chunk_idxes: &[usize], chunk_capacity: usize, values: Vec<TComponent>) -> Vec<(usize, Range<usize>)> {
+    let change_tick = components.change_tick;
+
+    let mut values = values.into_iter();
+    let mut ranges = vec![];
+
+    for chunk_idx in chunk_idxes {
+        if values.as_slice().is_empty() {
+            break;
+        }
+
+        let rows = chunk::extend(&mut components.chunks[*chunk_idx], &mut values, change_tick);
+
+        if !rows.is_empty() {
+            ranges.push((*chunk_idx, rows));
+        }
+    }
+
+    components.chunks.reserve(values.len().div_ceil(chunk_capacity));
+
+    while !values.as_slice().is_empty() {
+        let mut chunk = chunk::new_with_capacity(chunk_capacity);
+
+        let rows = chunk::extend(&mut chunk, &mut values, change_tick);
+
+        components.chunks.push(chunk);
+        ranges.push((components.chunks.len() - 1, rows));
+    }
+
+    ranges
 }
 
 #[derive(Debug)]
@@ -29,15 +79,33 @@ pub enum PushError {
     InvalidChunkIndex { index: usize }
 }
 
+#[derive(Debug)]
+pub enum RemoveError {
+    InvalidChunkIndex { index: usize },
+    InvalidComponentIndex { index: usize },
+}
+
+#[derive(Debug, Clone, Copy)]
 pub struct ComponentAddress {
     chunk_idx: usize,
     component_idx: usize,
 }
 
+pub fn address(chunk_idx: usize, component_idx: usize) -> ComponentAddress {
+    ComponentAddress {
+        chunk_idx,
+        component_idx,
+    }
+}
+
 pub fn chunk_idx(component_address: &ComponentAddress) -> usize {
     component_address.chunk_idx
 }
 
+pub fn component_idx(component_address: &ComponentAddress) -> usize {
+    component_address.component_idx
+}
+
 pub enum PushComponentAction {
     NewChunk { address: ComponentAddress },
     PushToChunk { address: ComponentAddress },
@@ -47,7 +115,16 @@ pub trait IComponents: Any + Sync + Send + Debug {
     fn as_any(&self) -> &dyn Any;
     fn as_mut_any(&mut self) -> &mut dyn Any;
 
-    fn push(&mut self, component: Box<dyn Any>, chunk_idxes: &[usize]) -> Result<PushComponentAction, PushError>;
+    fn set_change_tick(&mut self, change_tick: u64);
+
+    fn chunk_ticks(&self, chunk_idx: usize) -> Option<ChunkTicks>;
+
+    // chunk_capacity - вместимость чанков архетипа, одинаковая для всех его колонок;
+    // ticks - тики строки: текущий тик колонки для новой строки или тики исходного чанка при переезде
+    fn push(&mut self, component: Box<dyn Any>, chunk_idxes: &[usize], chunk_capacity: usize, ticks: Option<ChunkTicks>) -> Result<PushComponentAction, PushError>;
+
+    // удаляет компонент по адресу, перенося на его место последний компонент из чанков архетипа
+    fn swap_remove(&mut self, address: &ComponentAddress, chunk_idxes: &[usize]) -> Result<Box<dyn IUknownComponent>, RemoveError>;
 }
 
 impl<TComponent: 'static + Sync + Send + TypeUuid + Debug> IComponents for Components<TComponent> {
@@ -59,18 +136,28 @@ impl<TComponent: 'static + Sync + Send + TypeUuid + Debug> IComponents for Compo
         self as &mut dyn Any
     }
 
-    fn push(&mut self, component: Box<dyn Any>, chunk_idxes: &[usize]) -> Result<PushComponentAction, PushError> {
+    fn set_change_tick(&mut self, change_tick: u64) {
+        self.change_tick = change_tick;
+    }
+
+    fn chunk_ticks(&self, chunk_idx: usize) -> Option<ChunkTicks> {
+        self.chunks.get(chunk_idx).map(chunk::ticks)
+    }
+
+    fn push(&mut self, component: Box<dyn Any>, chunk_idxes: &[usize], chunk_capacity: usize, ticks: Option<ChunkTicks>) -> Result<PushComponentAction, PushError> {
         let component = component.downcast::<TComponent>()
             .map_err(|_| PushError::InvalidComponentType { expected: TypeInfo::from_type::<TComponent>() })?;
 
         let component = *component;
 
+        let ticks = ticks.unwrap_or(ChunkTicks { added: self.change_tick, changed: self.change_tick });
+
         for chunk_idx in chunk_idxes {
             let chunk = self.chunks.get_mut(*chunk_idx)
                 .ok_or_else(|| PushError::InvalidChunkIndex { index: *chunk_idx })?;
 
             if !chunk::is_full_filled(chunk) {
-                let component_idx = chunk::push(chunk, component);
+                let component_idx = chunk::push_with_ticks(chunk, component, ticks);
 
                 return Ok(PushComponentAction::PushToChunk {
                     address: ComponentAddress {
@@ -81,9 +168,9 @@ impl<TComponent: 'static + Sync + Send + TypeUuid + Debug> IComponents for Compo
             }
         }
         
-        let mut chunk = chunk::new_with_capacity(32);
+        let mut chunk = chunk::new_with_capacity(chunk_capacity);
 
-        let component_idx = chunk::push(&mut chunk, component);
+        let component_idx = chunk::push_with_ticks(&mut chunk, component, ticks);
 
         self.chunks.push(chunk);
 
@@ -96,6 +183,47 @@ impl<TComponent: 'static + Sync + Send + TypeUuid + Debug> IComponents for Compo
             }
         });
     }
+
+    fn swap_remove(&mut self, address: &ComponentAddress, chunk_idxes: &[usize]) -> Result<Box<dyn IUknownComponent>, RemoveError> {
+        let chunk = self.chunks.get(address.chunk_idx)
+            .ok_or(RemoveError::InvalidChunkIndex { index: address.chunk_idx })?;
+
+        if address.component_idx >= chunk::len(chunk) {
+            return Err(RemoveError::InvalidComponentIndex { index: address.component_idx });
+        }
+
+        // чанки архетипа заполняются по порядку, поэтому последний компонент лежит в последнем непустом чанке
+        let mut last_chunk_idx = None;
+
+        for chunk_idx in chunk_idxes.iter().rev() {
+            let chunk = self.chunks.get(*chunk_idx)
+                .ok_or(RemoveError::InvalidChunkIndex { index: *chunk_idx })?;
+
+            if !chunk::is_empty(chunk) {
+                last_chunk_idx = Some(*chunk_idx);
+                break;
+            }
+        }
+
+        let last_chunk_idx = last_chunk_idx.ok_or(RemoveError::InvalidChunkIndex { index: address.chunk_idx })?;
+
+        let last_component = chunk::pop(&mut self.chunks[last_chunk_idx])
+            .ok_or(RemoveError::InvalidChunkIndex { index: last_chunk_idx })?;
+
+        if last_chunk_idx == address.chunk_idx && chunk::len(&self.chunks[last_chunk_idx]) == address.component_idx {
+            return Ok(Box::new(last_component));
+        }
+
+        let chunk = &mut self.chunks[address.chunk_idx];
+
+        // на место удаленной строки переехала другая, для наблюдателей чанк изменился
+        chunk::mark_changed(chunk, self.change_tick);
+
+        let removed = chunk::replace(chunk, address.component_idx, last_component)
+            .ok_or(RemoveError::InvalidComponentIndex { index: address.component_idx })?;
+
+        Ok(Box::new(removed))
+    }
 }
 
 // pub fn push<TComponent>(components: &mut Components<TComponent>, archetype_chunks_ids: &HashSet<usize>) -> usize {
@@ -1,20 +1,245 @@
+use std::sync::{atomic::{AtomicU32, Ordering}, Arc, Mutex};
+
+use serde::{Serialize, Deserialize};
 use type_uuid::TypeUuid;
 use uuid::Uuid;
 
-use crate::world::{World, self};
+use crate::world::EntityLocation;
 
-#[derive(Debug, TypeUuid, Clone, Copy)]
+// индекс слота и поколение: после удаления сущности поколение слота растет, старые идентификаторы перестают быть живыми
+#[derive(Debug, TypeUuid, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 #[uuid = "2ac0c046-bf65-4857-9095-0137d418520c"]
-pub struct EntityId(Uuid);
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+// глобально уникальный идентификатор для сохранения и сети, навешивается на сущность как обычный компонент
+#[derive(Debug, TypeUuid, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[uuid = "7d4c1a2b-3e5f-4a6b-9c8d-0e1f2a3b4c5d"]
+pub struct GlobalId(pub Uuid);
+
+#[derive(Debug, Default)]
+pub struct Entities {
+    slots: Vec<EntitySlot>,
+    // освободившиеся слоты, поколение в них уже увеличено
+    free: Vec<u32>,
+    // слоты за концом slots, выданные через &World; становятся частью slots при следующем изменении мира
+    reserved: AtomicU32,
+    // слоты брошенных резервов, возвращаются в список свободных при следующем изменении мира
+    abandoned: Arc<Mutex<Vec<u32>>>,
+}
+
+// поколения слотов и список свободных для снимка мира: без них идентификатор, сохраненный внутри компонента,
+// после загрузки мог бы совпасть с новой сущностью
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntitiesSnapshot {
+    generations: Vec<u32>,
+    free: Vec<u32>,
+}
+
+// резерв, который возвращает слот в мир, если его бросили, так и не заняв
+#[derive(Debug)]
+pub struct Reservation {
+    entity_id: EntityId,
+    abandoned: Option<Arc<Mutex<Vec<u32>>>>,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Some(abandoned) = self.abandoned.take() {
+            abandoned.lock().unwrap().push(self.entity_id.index);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct EntitySlot {
+    generation: u32,
+    location: Option<EntityLocation>,
+}
+
+pub fn index(entity_id: EntityId) -> u32 {
+    entity_id.index
+}
+
+pub fn generation(entity_id: EntityId) -> u32 {
+    entity_id.generation
+}
+
+pub fn from_raw(index: u32, generation: u32) -> EntityId {
+    EntityId {
+        index,
+        generation,
+    }
+}
 
-pub fn new() -> EntityId {
-    EntityId(Uuid::new_v4())
+pub fn new_global_id() -> GlobalId {
+    GlobalId(Uuid::new_v4())
 }
 
-// pub fn new(world: &mut World) -> EntityId {
-//     let id = EntityId(Uuid::new_v4());
+pub fn alloc(entities: &mut Entities) -> EntityId {
+    flush(entities);
 
-//     let archetype = world::empty_archetype(world);
+    while let Some(index) = entities.free.pop() {
+        let slot = &entities.slots[index as usize];
 
-//     id
-// }
\ No newline at end of file
+        // слот мог быть занят напрямую через place, тогда в списке осталась устаревшая запись
+        if slot.location.is_none() {
+            return from_raw(index, slot.generation);
+        }
+    }
+
+    entities.slots.push(EntitySlot::default());
+
+    from_raw(entities.slots.len() as u32 - 1, 0)
+}
+
+// выдача идентификатора без &mut, например из буфера команд во время работы систем;
+// переиспользуются только новые слоты, список свободных не трогается.
+// слот такого идентификатора не освобождается сам: его нужно занять через place
+pub fn reserve(entities: &Entities) -> EntityId {
+    let index = entities.slots.len() as u32 + entities.reserved.fetch_add(1, Ordering::AcqRel);
+
+    from_raw(index, 0)
+}
+
+// как reserve, но брошенный незанятым резерв вернет слот в список свободных
+pub fn reservation(entities: &Entities) -> Reservation {
+    Reservation {
+        entity_id: reserve(entities),
+        abandoned: Some(entities.abandoned.clone()),
+    }
+}
+
+pub fn reservation_id(reservation: &Reservation) -> EntityId {
+    reservation.entity_id
+}
+
+// резерв больше не вернет слот: идентификатор сейчас займут
+pub fn fulfill(mut reservation: Reservation) -> EntityId {
+    reservation.abandoned = None;
+    reservation.entity_id
+}
+
+fn flush(entities: &mut Entities) {
+    let reserved = std::mem::take(entities.reserved.get_mut());
+
+    entities.slots.extend((0..reserved).map(|_| EntitySlot::default()));
+
+    let abandoned = std::mem::take(&mut *entities.abandoned.lock().unwrap());
+
+    for index in abandoned {
+        let slot = &mut entities.slots[index as usize];
+
+        // идентификатор резерва уже мог разойтись по командам и компонентам, поэтому слот идет дальше со следующим поколением
+        if slot.location.is_none() {
+            slot.generation = slot.generation.wrapping_add(1);
+            entities.free.push(index);
+        }
+    }
+}
+
+// слоты снимаются так, как их увидел бы следующий flush: резервы становятся слотами, брошенные резервы - свободными
+pub fn snapshot(entities: &Entities) -> EntitiesSnapshot {
+    let mut generations = entities.slots.iter()
+        .map(|slot| slot.generation)
+        .collect::<Vec<_>>();
+
+    generations.extend((0..entities.reserved.load(Ordering::Acquire)).map(|_| 0));
+
+    let mut free = entities.free.clone();
+
+    for index in entities.abandoned.lock().unwrap().iter() {
+        if entities.slots.get(*index as usize).is_none_or(|slot| slot.location.is_none()) {
+            generations[*index as usize] = generations[*index as usize].wrapping_add(1);
+            free.push(*index);
+        }
+    }
+
+    EntitiesSnapshot { generations, free }
+}
+
+// слоты из снимка пока пусты, сущности занимают их через place с сохраненными идентификаторами;
+// false, если в хранилище уже есть слоты
+pub fn restore(entities: &mut Entities, snapshot: EntitiesSnapshot) -> bool {
+    flush(entities);
+
+    if !entities.slots.is_empty() {
+        return false;
+    }
+
+    entities.slots = snapshot.generations.into_iter()
+        .map(|generation| EntitySlot { generation, location: None })
+        .collect();
+    entities.free = snapshot.free;
+
+    true
+}
+
+// занимает слот под заранее выданный или загруженный идентификатор; false, если слот жив или идентификатор устарел
+pub fn place(entities: &mut Entities, entity_id: EntityId, location: EntityLocation) -> bool {
+    flush(entities);
+
+    let index = entity_id.index as usize;
+
+    // пропущенные слоты, например при загрузке снимка, сразу считаются свободными;
+    // сам занимаемый слот в список не попадает, иначе после free он окажется там дважды
+    while entities.slots.len() < index {
+        entities.free.push(entities.slots.len() as u32);
+        entities.slots.push(EntitySlot::default());
+    }
+
+    if entities.slots.len() == index {
+        entities.slots.push(EntitySlot::default());
+    }
+
+    let slot = &mut entities.slots[index];
+
+    if slot.location.is_some() || slot.generation > entity_id.generation {
+        return false;
+    }
+
+    slot.generation = entity_id.generation;
+    slot.location = Some(location);
+
+    true
+}
+
+pub fn free(entities: &mut Entities, entity_id: EntityId) -> Option<EntityLocation> {
+    let slot = entities.slots.get_mut(entity_id.index as usize)?;
+
+    if slot.generation != entity_id.generation {
+        return None;
+    }
+
+    let location = slot.location.take()?;
+
+    slot.generation = slot.generation.wrapping_add(1);
+    entities.free.push(entity_id.index);
+
+    Some(location)
+}
+
+pub fn is_alive(entities: &Entities, entity_id: EntityId) -> bool {
+    location(entities, entity_id).is_some()
+}
+
+pub fn location(entities: &Entities, entity_id: EntityId) -> Option<&EntityLocation> {
+    let slot = entities.slots.get(entity_id.index as usize)?;
+
+    if slot.generation != entity_id.generation {
+        return None;
+    }
+
+    slot.location.as_ref()
+}
+
+// перенос строки внутри хранилища, идентификатор при этом не меняется
+pub fn set_location(entities: &mut Entities, entity_id: EntityId, location: EntityLocation) {
+    let slot = &mut entities.slots[entity_id.index as usize];
+
+    debug_assert_eq!(slot.generation, entity_id.generation);
+
+    slot.location = Some(location);
+}
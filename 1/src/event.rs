@@ -0,0 +1,98 @@
+use std::{any::Any, fmt::Debug, marker::PhantomData};
+
+use type_uuid::TypeUuid;
+
+// двойной буфер событий: события живут текущий и следующий кадр, потом отбрасываются
+#[derive(Debug)]
+pub struct Events<TEvent> where TEvent: Sync + Send + TypeUuid + Debug {
+    previous: Vec<TEvent>,
+    current: Vec<TEvent>,
+    // порядковые номера первых событий буферов, по ним читатели понимают, что уже прочитано
+    previous_start: usize,
+    current_start: usize,
+}
+
+// позиция читателя в канале, хранится у самой системы между кадрами
+#[derive(Debug)]
+pub struct EventCursor<TEvent> {
+    next: usize,
+    event: PhantomData<TEvent>,
+}
+
+impl<TEvent> Default for EventCursor<TEvent> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            event: PhantomData,
+        }
+    }
+}
+
+pub fn new<TEvent: Sync + Send + TypeUuid + Debug>() -> Events<TEvent> {
+    Events::<TEvent> {
+        previous: vec![],
+        current: vec![],
+        previous_start: 0,
+        current_start: 0,
+    }
+}
+
+pub fn cursor<TEvent>() -> EventCursor<TEvent> {
+    EventCursor::default()
+}
+
+pub fn send<TEvent: Sync + Send + TypeUuid + Debug>(events: &mut Events<TEvent>, event: TEvent) {
+    events.current.push(event);
+}
+
+// события, отправленные после прошлого чтения этим курсором; пропущенные дольше двух кадров уже потеряны
+pub fn read<'events, TEvent: Sync + Send + TypeUuid + Debug>(events: &'events Events<TEvent>, cursor: &mut EventCursor<TEvent>) -> impl Iterator<Item = &'events TEvent> {
+    let next = cursor.next.max(events.previous_start);
+
+    cursor.next = events.current_start + events.current.len();
+
+    let previous = events.previous.get(next.saturating_sub(events.previous_start)..).unwrap_or_default();
+    let current = events.current.get(next.saturating_sub(events.current_start)..).unwrap_or_default();
+
+    previous.iter().chain(current.iter())
+}
+
+// все события, еще живые в буферах
+pub fn iter<TEvent: Sync + Send + TypeUuid + Debug>(events: &Events<TEvent>) -> impl Iterator<Item = &TEvent> {
+    events.previous.iter().chain(events.current.iter())
+}
+
+pub fn len<TEvent: Sync + Send + TypeUuid + Debug>(events: &Events<TEvent>) -> usize {
+    events.previous.len() + events.current.len()
+}
+
+pub fn is_empty<TEvent: Sync + Send + TypeUuid + Debug>(events: &Events<TEvent>) -> bool {
+    len(events) == 0
+}
+
+// граница кадра: события прошлого кадра отбрасываются, текущие становятся прошлыми
+pub fn update<TEvent: Sync + Send + TypeUuid + Debug>(events: &mut Events<TEvent>) {
+    events.previous = std::mem::take(&mut events.current);
+    events.previous_start = events.current_start;
+    events.current_start += events.previous.len();
+}
+
+pub trait IEvents: Any + Sync + Send + Debug {
+    fn as_any(&self) -> &dyn Any;
+    fn as_mut_any(&mut self) -> &mut dyn Any;
+    fn update(&mut self);
+}
+
+impl<TEvent: 'static + Sync + Send + TypeUuid + Debug> IEvents for Events<TEvent> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_mut_any(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn update(&mut self) {
+        update(self);
+    }
+}
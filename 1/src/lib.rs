@@ -13,3 +13,8 @@ pub mod unknown_component;
 pub mod call;
 pub mod scheduler;
 pub mod system;
+pub mod commands;
+pub mod event;
+pub mod snapshot;
+pub mod registry;
+pub mod sync;
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+// плотный номер компонента внутри мира; uuid хешируется один раз при регистрации, дальше сравниваются номера
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentId(u32);
+
+#[derive(Debug, Default)]
+pub struct ComponentRegistry {
+    ids: HashMap<Uuid, ComponentId>,
+    uuids: Vec<Uuid>,
+}
+
+// набор компонентов архетипа битовой маской: бит с номером ComponentId
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Signature {
+    words: Vec<u64>,
+}
+
+pub fn index(component_id: ComponentId) -> u32 {
+    component_id.0
+}
+
+pub fn intern(registry: &mut ComponentRegistry, component_uuid: Uuid) -> ComponentId {
+    if let Some(component_id) = registry.ids.get(&component_uuid) {
+        return *component_id;
+    }
+
+    let component_id = ComponentId(registry.uuids.len() as u32);
+
+    registry.ids.insert(component_uuid, component_id);
+    registry.uuids.push(component_uuid);
+
+    component_id
+}
+
+pub fn component_id(registry: &ComponentRegistry, component_uuid: Uuid) -> Option<ComponentId> {
+    registry.ids.get(&component_uuid).copied()
+}
+
+pub fn uuid(registry: &ComponentRegistry, component_id: ComponentId) -> Option<Uuid> {
+    registry.uuids.get(component_id.0 as usize).copied()
+}
+
+pub fn len(registry: &ComponentRegistry) -> usize {
+    registry.uuids.len()
+}
+
+pub fn is_empty(registry: &ComponentRegistry) -> bool {
+    registry.uuids.is_empty()
+}
+
+pub fn signature(component_ids: impl IntoIterator<Item = ComponentId>) -> Signature {
+    let mut signature = Signature::default();
+
+    for component_id in component_ids {
+        insert(&mut signature, component_id);
+    }
+
+    signature
+}
+
+// биты только добавляются, поэтому хвостовых нулевых слов не бывает и равные наборы дают равные маски
+pub fn insert(signature: &mut Signature, component_id: ComponentId) {
+    let (word, bit) = position(component_id);
+
+    if signature.words.len() <= word {
+        signature.words.resize(word + 1, 0);
+    }
+
+    signature.words[word] |= bit;
+}
+
+pub fn contains(signature: &Signature, component_id: ComponentId) -> bool {
+    let (word, bit) = position(component_id);
+
+    signature.words.get(word).is_some_and(|x| x & bit != 0)
+}
+
+// все компоненты required есть в signature, проверка идет по словам маски
+pub fn is_superset(signature: &Signature, required: &Signature) -> bool {
+    required.words.len() <= signature.words.len() &&
+    required.words.iter()
+        .zip(signature.words.iter())
+        .all(|(required, word)| word & required == *required)
+}
+
+pub fn ids(signature: &Signature) -> impl Iterator<Item = ComponentId> + '_ {
+    signature.words.iter()
+        .enumerate()
+        .flat_map(|(word_idx, word)| (0..64)
+            .filter(move |bit| word & (1 << bit) != 0)
+            .map(move |bit| ComponentId((word_idx * 64 + bit) as u32)))
+}
+
+fn position(component_id: ComponentId) -> (usize, u64) {
+    (component_id.0 as usize / 64, 1 << (component_id.0 % 64))
+}
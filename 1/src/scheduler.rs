@@ -1,12 +1,333 @@
-// use std::{collections::HashMap, any::TypeId};
+use std::{collections::HashMap, sync::Arc};
 
-// use tokio::sync::oneshot::{Sender, Receiver};
+use futures::{future::BoxFuture, lock::Mutex, stream::FuturesUnordered, StreamExt};
+use uuid::Uuid;
 
+use crate::{world::{World, self}, system::{ISystem, ComponentAccess, self}, commands::{Commands, self}, type_info::TypeInfo, sync::{RwLock, self}};
 
-// pub struct Scheduler {
-//     systems: HashMap<TypeId, (Option<Sender<bool>>, Receiver<bool>)>,
-// }
+#[derive(Default)]
+pub struct Scheduler {
+    systems: Vec<ScheduledSystem>,
+    system_idxes: HashMap<Uuid, usize>,
+    // (кто раньше, кто позже)
+    constraints: Vec<(Uuid, Uuid)>,
+    // для каждой системы - индексы систем, которые ждут её завершения
+    graph: Option<Vec<Vec<usize>>>,
+    // поколение архетипов мира, под которое построен граф; None - граф построен без мира, по одним типам
+    generation: Option<usize>,
+    conflicts: Vec<AccessConflict>,
+}
 
-// pub async fn after() -> bool {
+struct ScheduledSystem {
+    type_info: TypeInfo,
+    access: ComponentAccess,
+    slot: Arc<Mutex<SystemSlot>>,
+}
 
-// }
\ No newline at end of file
+// система не покидает слот: задача кадра держит замок, пока система работает, поэтому ни паника,
+// ни брошенный run не оставляют планировщик без системы. следующий кадр дождется недоработавшую задачу
+struct SystemSlot {
+    system: Box<dyn IScheduledSystem + Send>,
+    commands: Commands,
+}
+
+// системы без явного порядка, которым нужны одни и те же колонки; планировщик запускает их по очереди в порядке регистрации.
+// при сборке под мир колонки общие, только если обе системы могут попасть в один архетип
+#[derive(Debug, Clone)]
+pub struct AccessConflict {
+    pub first: TypeInfo,
+    pub second: TypeInfo,
+    pub components: Vec<Uuid>,
+}
+
+#[derive(Debug)]
+pub enum ScheduleError {
+    DuplicateSystem { system: TypeInfo },
+    UnknownSystem { system: Uuid },
+    // системы цикла в порядке зависимостей, первая повторяется в конце
+    Cycle { systems: Vec<TypeInfo> },
+    // система запаниковала; зависящие от неё системы в этом кадре не запускались, её команды отброшены
+    SystemPanicked { system: TypeInfo },
+}
+
+trait IScheduledSystem {
+    fn run<'run>(&'run mut self, world: &'run World, commands: &'run mut Commands) -> BoxFuture<'run, ()>;
+}
+
+impl<TSystem: ISystem> IScheduledSystem for TSystem {
+    fn run<'run>(&'run mut self, world: &'run World, commands: &'run mut Commands) -> BoxFuture<'run, ()> {
+        Box::pin(async move {
+            let Some(props) = self.query(world).await else {
+                return;
+            };
+
+            self.system(props, world, commands).await;
+        })
+    }
+}
+
+pub fn new() -> Scheduler {
+    Scheduler::default()
+}
+
+pub fn add_system<TSystem: ISystem + 'static>(scheduler: &mut Scheduler, system: TSystem) -> Result<(), ScheduleError> {
+    let system_uuid = Uuid::from_bytes(TSystem::UUID);
+
+    if scheduler.system_idxes.contains_key(&system_uuid) {
+        return Err(ScheduleError::DuplicateSystem { system: TypeInfo::from_type::<TSystem>() });
+    }
+
+    scheduler.system_idxes.insert(system_uuid, scheduler.systems.len());
+    scheduler.systems.push(ScheduledSystem {
+        type_info: TypeInfo::from_type::<TSystem>(),
+        access: system::access::<TSystem>(),
+        slot: Arc::new(Mutex::new(SystemSlot {
+            system: Box::new(system),
+            commands: commands::new(),
+        })),
+    });
+
+    scheduler.graph = None;
+
+    Ok(())
+}
+
+// TFirst завершится до старта TSecond
+pub fn before<TFirst: ISystem, TSecond: ISystem>(scheduler: &mut Scheduler) {
+    scheduler.constraints.push((Uuid::from_bytes(TFirst::UUID), Uuid::from_bytes(TSecond::UUID)));
+    scheduler.graph = None;
+}
+
+// TSecond стартует после завершения TFirst
+pub fn after<TSecond: ISystem, TFirst: ISystem>(scheduler: &mut Scheduler) {
+    before::<TFirst, TSecond>(scheduler);
+}
+
+// граф без учета мира: конфликтом считается любое пересечение по типам
+pub fn build(scheduler: &mut Scheduler) -> Result<(), ScheduleError> {
+    build_graph(scheduler, None)
+}
+
+// граф под текущие архетипы мира: системы, пишущие один тип в непересекающихся архетипах, идут параллельно;
+// run пересобирает его сам, когда в мире появляются новые архетипы
+pub fn build_for_world(scheduler: &mut Scheduler, world: &World) -> Result<(), ScheduleError> {
+    build_graph(scheduler, Some(world))
+}
+
+fn build_graph(scheduler: &mut Scheduler, world: Option<&World>) -> Result<(), ScheduleError> {
+    let mut graph = vec![Vec::new(); scheduler.systems.len()];
+
+    for (first, second) in scheduler.constraints.iter() {
+        let first_idx = *scheduler.system_idxes.get(first)
+            .ok_or(ScheduleError::UnknownSystem { system: *first })?;
+        let second_idx = *scheduler.system_idxes.get(second)
+            .ok_or(ScheduleError::UnknownSystem { system: *second })?;
+
+        if !graph[first_idx].contains(&second_idx) {
+            graph[first_idx].push(second_idx);
+        }
+    }
+
+    if let Some(cycle) = find_cycle(&graph) {
+        return Err(ScheduleError::Cycle {
+            systems: cycle.into_iter()
+                .map(|idx| scheduler.systems[idx].type_info)
+                .collect(),
+        });
+    }
+
+    let mut conflicts = Vec::new();
+
+    for first_idx in 0..scheduler.systems.len() {
+        for second_idx in first_idx + 1..scheduler.systems.len() {
+            let first = &scheduler.systems[first_idx];
+            let second = &scheduler.systems[second_idx];
+
+            let components = match world {
+                Some(world) => system::world_conflicts(&first.access, &second.access, world),
+                None => system::conflicts(&first.access, &second.access),
+            };
+
+            if components.is_empty() {
+                continue;
+            }
+
+            // если порядок уже задан через зависимости, системы и так не пересекаются по времени
+            if has_path(&graph, first_idx, second_idx) || has_path(&graph, second_idx, first_idx) {
+                continue;
+            }
+
+            // пути между системами нет ни в одну сторону, так что новое ребро не может замкнуть цикл
+            graph[first_idx].push(second_idx);
+
+            conflicts.push(AccessConflict {
+                first: first.type_info,
+                second: second.type_info,
+                components,
+            });
+        }
+    }
+
+    scheduler.graph = Some(graph);
+    scheduler.generation = world.map(world::archetype_generation);
+    scheduler.conflicts = conflicts;
+
+    Ok(())
+}
+
+pub fn conflicts(scheduler: &Scheduler) -> &[AccessConflict] {
+    &scheduler.conflicts
+}
+
+fn has_path(graph: &[Vec<usize>], from: usize, to: usize) -> bool {
+    let mut visited = vec![false; graph.len()];
+    let mut stack = vec![from];
+
+    while let Some(node) = stack.pop() {
+        if node == to {
+            return true;
+        }
+
+        if std::mem::replace(&mut visited[node], true) {
+            continue;
+        }
+
+        stack.extend(graph[node].iter().copied());
+    }
+
+    false
+}
+
+fn find_cycle(graph: &[Vec<usize>]) -> Option<Vec<usize>> {
+    // 0 - не посещена, 1 - в текущем пути, 2 - обработана
+    let mut state = vec![0u8; graph.len()];
+    let mut path = Vec::new();
+
+    fn visit(node: usize, graph: &[Vec<usize>], state: &mut [u8], path: &mut Vec<usize>) -> Option<Vec<usize>> {
+        state[node] = 1;
+        path.push(node);
+
+        for next in graph[node].iter() {
+            match state[*next] {
+                0 => {
+                    if let Some(cycle) = visit(*next, graph, state, path) {
+                        return Some(cycle);
+                    }
+                },
+                1 => {
+                    let start = path.iter().position(|x| x == next).unwrap();
+                    let mut cycle = path[start..].to_vec();
+                    cycle.push(*next);
+                    return Some(cycle);
+                },
+                _ => {},
+            }
+        }
+
+        state[node] = 2;
+        path.pop();
+
+        None
+    }
+
+    for node in 0..graph.len() {
+        if state[node] == 0 {
+            if let Some(cycle) = visit(node, graph, &mut state, &mut path) {
+                return Some(cycle);
+            }
+        }
+    }
+
+    None
+}
+
+// один кадр: система стартует, как только завершились все системы, которые должны идти до неё;
+// каждая готовая система запускается отдельной задачей, так что независимые системы исполняются
+// параллельно на рабочих потоках под общей блокировкой мира на чтение.
+// после завершения всех систем события переходят в следующий кадр,
+// а команды применяются под блокировкой на запись в порядке регистрации систем.
+// после паники системы новые системы кадра не стартуют, уже запущенные дорабатывают, и run возвращает SystemPanicked
+pub async fn run(scheduler: &mut Scheduler, world: &Arc<RwLock<World>>) -> Result<(), ScheduleError> {
+    let mut panicked = None;
+
+    {
+        // задачи живут дольше заимствования, поэтому блокировка на чтение держится через Arc
+        let world = Arc::new(sync::read_owned(world).await);
+
+        // новые архетипы появляются только при применении команд, так что в пределах кадра граф не устаревает
+        if scheduler.graph.is_none() || scheduler.generation != Some(world::archetype_generation(&world)) {
+            build_for_world(scheduler, &world)?;
+        }
+
+        let graph = scheduler.graph.as_ref().unwrap();
+
+        let mut in_degree = vec![0usize; graph.len()];
+
+        for next in graph.iter().flatten() {
+            in_degree[*next] += 1;
+        }
+
+        let mut running = FuturesUnordered::new();
+
+        let start = |idx: usize, systems: &Vec<ScheduledSystem>| {
+            let slot = systems[idx].slot.clone();
+            let world = world.clone();
+
+            let task = sync::spawn(async move {
+                let mut slot = slot.lock().await;
+                let SystemSlot { system, commands } = &mut *slot;
+
+                system.run(&world, commands).await;
+            });
+
+            async move { (idx, task.await.is_some()) }
+        };
+
+        for (idx, degree) in in_degree.iter().enumerate() {
+            if *degree == 0 {
+                running.push(start(idx, &scheduler.systems));
+            }
+        }
+
+        while let Some((idx, completed)) = running.next().await {
+            if !completed {
+                // замок освободился при раскрутке паники, недописанные команды системы не применяются
+                scheduler.systems[idx].slot.lock().await.commands = commands::new();
+                panicked.get_or_insert(scheduler.systems[idx].type_info);
+            }
+
+            if panicked.is_some() {
+                continue;
+            }
+
+            for next in graph[idx].iter() {
+                in_degree[*next] -= 1;
+
+                if in_degree[*next] == 0 {
+                    running.push(start(*next, &scheduler.systems));
+                }
+            }
+        }
+
+        world::update_events(&world).await;
+    }
+
+    let mut slots = Vec::with_capacity(scheduler.systems.len());
+
+    for system in scheduler.systems.iter() {
+        slots.push(system.slot.lock().await);
+    }
+
+    if slots.iter().any(|slot| !commands::is_empty(&slot.commands)) {
+        let mut world = world.write().await;
+
+        for slot in slots.iter_mut() {
+            commands::apply(&mut slot.commands, &mut world).await;
+        }
+    }
+
+    match panicked {
+        Some(system) => Err(ScheduleError::SystemPanicked { system }),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,290 @@
+use std::{collections::{HashMap, BTreeSet}, fmt::Debug};
+
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use type_uuid::TypeUuid;
+use uuid::Uuid;
+
+use crate::{world::{World, self}, archetype, component::{Components, IComponents, self}, chunk, entity::{EntityId, EntitiesSnapshot}, type_info::TypeInfo, unknown_component::IUknownComponent};
+
+const VERSION: u32 = 2;
+
+// компоненты, которые умеют сохраняться; идентичность на диске - TypeUuid компонента
+pub struct SnapshotRegistry {
+    components: HashMap<Uuid, SnapshotComponent>,
+}
+
+type Rows = Vec<Box<dyn IUknownComponent>>;
+type SaveColumn<TColumn> = fn(&dyn IComponents, &[usize]) -> Result<TColumn, SnapshotError>;
+
+struct SnapshotComponent {
+    type_info: TypeInfo,
+    to_json: SaveColumn<serde_json::Value>,
+    from_json: fn(serde_json::Value) -> Result<Rows, SnapshotError>,
+    to_binary: SaveColumn<Vec<u8>>,
+    from_binary: fn(&[u8]) -> Result<Rows, SnapshotError>,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    // в мире есть колонки, которые не зарегистрированы для сохранения
+    UnregisteredComponents { components: Vec<Uuid> },
+    // в снимке есть колонки, которых не знает реестр загружающей стороны
+    UnknownComponents { components: Vec<Uuid> },
+    UnsupportedVersion { version: u32 },
+    MissingEntityIds { archetype: Vec<Uuid> },
+    RowCountMismatch { component: Uuid, expected: usize, found: usize },
+    DuplicateEntity { entity_id: EntityId },
+    Json { message: String },
+    Binary { message: String },
+}
+
+// колонки архетипа хранятся целиком: строки всех чанков подряд, в порядке чанков архетипа
+#[derive(Debug, Serialize, Deserialize)]
+struct WorldSnapshot<TColumn> {
+    version: u32,
+    // поколения слотов и свободные слоты, чтобы идентификаторы удаленных сущностей не ожили после загрузки
+    entities: EntitiesSnapshot,
+    archetypes: Vec<ArchetypeSnapshot<TColumn>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchetypeSnapshot<TColumn> {
+    columns: Vec<ColumnSnapshot<TColumn>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ColumnSnapshot<TColumn> {
+    component: Uuid,
+    rows: TColumn,
+}
+
+pub fn new() -> SnapshotRegistry {
+    let mut registry = SnapshotRegistry {
+        components: HashMap::new(),
+    };
+
+    register::<EntityId>(&mut registry);
+
+    registry
+}
+
+pub fn register<TComponent>(registry: &mut SnapshotRegistry)
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug + Serialize + DeserializeOwned,
+{
+    registry.components.insert(Uuid::from_bytes(TComponent::UUID), SnapshotComponent {
+        type_info: TypeInfo::from_type::<TComponent>(),
+        to_json: column_to_json::<TComponent>,
+        from_json: column_from_json::<TComponent>,
+        to_binary: column_to_binary::<TComponent>,
+        from_binary: column_from_binary::<TComponent>,
+    });
+}
+
+pub fn is_registered(registry: &SnapshotRegistry, component_uuid: Uuid) -> bool {
+    registry.components.contains_key(&component_uuid)
+}
+
+pub fn type_info(registry: &SnapshotRegistry, component_uuid: Uuid) -> Option<TypeInfo> {
+    registry.components.get(&component_uuid).map(|component| component.type_info)
+}
+
+pub async fn to_json(registry: &SnapshotRegistry, world: &World) -> Result<String, SnapshotError> {
+    let snapshot = save(registry, world, |component, components, chunk_ids| (component.to_json)(components, chunk_ids)).await?;
+
+    serde_json::to_string(&snapshot).map_err(json_error)
+}
+
+pub async fn from_json(registry: &SnapshotRegistry, json: &str) -> Result<World, SnapshotError> {
+    let snapshot: WorldSnapshot<serde_json::Value> = serde_json::from_str(json).map_err(json_error)?;
+
+    load(registry, snapshot, |component, rows| (component.from_json)(rows)).await
+}
+
+pub async fn to_binary(registry: &SnapshotRegistry, world: &World) -> Result<Vec<u8>, SnapshotError> {
+    let snapshot = save(registry, world, |component, components, chunk_ids| (component.to_binary)(components, chunk_ids)).await?;
+
+    bincode::serialize(&snapshot).map_err(binary_error)
+}
+
+pub async fn from_binary(registry: &SnapshotRegistry, bytes: &[u8]) -> Result<World, SnapshotError> {
+    let snapshot: WorldSnapshot<Vec<u8>> = bincode::deserialize(bytes).map_err(binary_error)?;
+
+    load(registry, snapshot, |component, rows| (component.from_binary)(&rows)).await
+}
+
+async fn save<TColumn>(
+    registry: &SnapshotRegistry,
+    world: &World,
+    column: impl Fn(&SnapshotComponent, &dyn IComponents, &[usize]) -> Result<TColumn, SnapshotError>,
+) -> Result<WorldSnapshot<TColumn>, SnapshotError> {
+    let mut archetypes = world::archetypes(world, &world::filter_archetypes(world, |_| true));
+
+    // порядок архетипов в снимке не зависит от порядка их создания
+    archetypes.sort_by_key(|archetype| archetype::components(archetype));
+
+    let unregistered = archetypes.iter()
+        .flat_map(|archetype| archetype::components(archetype))
+        .filter(|uuid| !is_registered(registry, **uuid))
+        .copied()
+        .collect::<BTreeSet<_>>();
+
+    if !unregistered.is_empty() {
+        return Err(SnapshotError::UnregisteredComponents { components: unregistered.into_iter().collect() });
+    }
+
+    let mut snapshots = Vec::with_capacity(archetypes.len());
+
+    for archetype in archetypes {
+        let key = archetype::components(archetype);
+        let mut columns = Vec::with_capacity(key.len());
+
+        // колонки архетипа блокируются по одной, в порядке uuid
+        for component_uuid in key.iter() {
+            let chunk_ids = archetype::chunk_ids(archetype, *component_uuid).unwrap();
+            let components = archetype::column(archetype, *component_uuid).unwrap().read().await;
+
+            columns.push(ColumnSnapshot {
+                component: *component_uuid,
+                rows: column(registry.components.get(component_uuid).unwrap(), &*components, chunk_ids)?,
+            });
+        }
+
+        snapshots.push(ArchetypeSnapshot { columns });
+    }
+
+    Ok(WorldSnapshot {
+        version: VERSION,
+        entities: world::entities_snapshot(world),
+        archetypes: snapshots,
+    })
+}
+
+async fn load<TColumn>(
+    registry: &SnapshotRegistry,
+    snapshot: WorldSnapshot<TColumn>,
+    column: impl Fn(&SnapshotComponent, TColumn) -> Result<Rows, SnapshotError>,
+) -> Result<World, SnapshotError> {
+    if snapshot.version != VERSION {
+        return Err(SnapshotError::UnsupportedVersion { version: snapshot.version });
+    }
+
+    // неизвестные колонки собираются по всему снимку до того, как что-то загружено
+    let unknown = snapshot.archetypes.iter()
+        .flat_map(|archetype| archetype.columns.iter())
+        .map(|column| column.component)
+        .filter(|uuid| !is_registered(registry, *uuid))
+        .collect::<BTreeSet<_>>();
+
+    if !unknown.is_empty() {
+        return Err(SnapshotError::UnknownComponents { components: unknown.into_iter().collect() });
+    }
+
+    let mut world = World::default();
+
+    world::restore_entities(&mut world, snapshot.entities);
+
+    for archetype in snapshot.archetypes {
+        let component_uuids = archetype.columns.iter()
+            .map(|column| column.component)
+            .collect::<Vec<_>>();
+
+        let mut entity_ids = None;
+        let mut columns = Vec::with_capacity(archetype.columns.len());
+
+        for ColumnSnapshot { component, rows } in archetype.columns {
+            let rows = column(registry.components.get(&component).unwrap(), rows)?;
+
+            if component == Uuid::from_bytes(EntityId::UUID) {
+                entity_ids = Some(rows.into_iter()
+                    .map(|row| *row.into_boxed().downcast::<EntityId>().unwrap())
+                    .collect::<Vec<_>>());
+            } else {
+                columns.push((component, rows.into_iter()));
+            }
+        }
+
+        let Some(entity_ids) = entity_ids else {
+            return Err(SnapshotError::MissingEntityIds { archetype: component_uuids });
+        };
+
+        for (component, rows) in columns.iter() {
+            if rows.len() != entity_ids.len() {
+                return Err(SnapshotError::RowCountMismatch { component: *component, expected: entity_ids.len(), found: rows.len() });
+            }
+        }
+
+        for entity_id in entity_ids {
+            let components = columns.iter_mut()
+                .map(|(_component, rows)| rows.next().unwrap())
+                .collect::<Vec<_>>();
+
+            if !world::add_entity_with_id(&mut world, entity_id, components).await {
+                return Err(SnapshotError::DuplicateEntity { entity_id });
+            }
+        }
+    }
+
+    Ok(world)
+}
+
+fn column_rows<'components, TComponent>(components: &'components dyn IComponents, chunk_ids: &[usize]) -> Vec<&'components TComponent>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    let components = components.as_any().downcast_ref::<Components<TComponent>>().unwrap();
+
+    chunk_ids.iter()
+        .filter_map(|chunk_id| component::chunk(components, *chunk_id))
+        .flat_map(|chunk| chunk::components(chunk).iter())
+        .collect()
+}
+
+fn into_unknown<TComponent>(rows: Vec<TComponent>) -> Rows
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    rows.into_iter()
+        .map(|row| Box::new(row) as Box<dyn IUknownComponent>)
+        .collect()
+}
+
+fn column_to_json<TComponent>(components: &dyn IComponents, chunk_ids: &[usize]) -> Result<serde_json::Value, SnapshotError>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug + Serialize,
+{
+    serde_json::to_value(column_rows::<TComponent>(components, chunk_ids)).map_err(json_error)
+}
+
+fn column_from_json<TComponent>(rows: serde_json::Value) -> Result<Rows, SnapshotError>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug + DeserializeOwned,
+{
+    let rows: Vec<TComponent> = serde_json::from_value(rows).map_err(json_error)?;
+
+    Ok(into_unknown(rows))
+}
+
+fn column_to_binary<TComponent>(components: &dyn IComponents, chunk_ids: &[usize]) -> Result<Vec<u8>, SnapshotError>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug + Serialize,
+{
+    bincode::serialize(&column_rows::<TComponent>(components, chunk_ids)).map_err(binary_error)
+}
+
+fn column_from_binary<TComponent>(rows: &[u8]) -> Result<Rows, SnapshotError>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug + DeserializeOwned,
+{
+    let rows: Vec<TComponent> = bincode::deserialize(rows).map_err(binary_error)?;
+
+    Ok(into_unknown(rows))
+}
+
+fn json_error(error: serde_json::Error) -> SnapshotError {
+    SnapshotError::Json { message: error.to_string() }
+}
+
+fn binary_error(error: bincode::Error) -> SnapshotError {
+    SnapshotError::Binary { message: error.to_string() }
+}
@@ -0,0 +1,190 @@
+// примитивы блокировок выбираются фичей: tokio (по умолчанию) или async-lock для smol и других рантаймов;
+// фичи взаимоисключающие, для async-lock нужен default-features = false, поэтому --all-features не собирается.
+// остальной код крейта видит только этот модуль
+#[cfg(not(any(feature = "tokio", feature = "async-lock")))]
+compile_error!("one of the features `tokio` or `async-lock` must be enabled");
+
+// фичи складываются, и без ошибки async-lock молча проиграл бы включенному по умолчанию tokio
+#[cfg(all(feature = "tokio", feature = "async-lock"))]
+compile_error!("features `tokio` and `async-lock` are mutually exclusive; enable `async-lock` with `default-features = false`");
+
+#[cfg(feature = "tokio")]
+mod backend {
+    use std::{future::Future, sync::Arc};
+
+    pub use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+    pub type OwnedReadGuard<T> = tokio::sync::OwnedRwLockReadGuard<T>;
+
+    pub type MappedReadGuard<'a, T> = tokio::sync::RwLockReadGuard<'a, T>;
+    pub type MappedWriteGuard<'a, T> = tokio::sync::RwLockMappedWriteGuard<'a, T>;
+
+    pub fn map_read<'a, T, U>(guard: RwLockReadGuard<'a, T>, f: impl FnOnce(&T) -> &U) -> MappedReadGuard<'a, U>
+    where
+        T: ?Sized + Sync + 'a,
+        U: ?Sized,
+    {
+        RwLockReadGuard::map(guard, f)
+    }
+
+    pub fn map_write<'a, T, U>(guard: RwLockWriteGuard<'a, T>, f: impl FnOnce(&mut T) -> &mut U) -> MappedWriteGuard<'a, U>
+    where
+        T: ?Sized + Send + Sync + 'a,
+        U: ?Sized,
+    {
+        RwLockWriteGuard::map(guard, f)
+    }
+
+    // None, если блокировка занята несовместимо или её уже ждет писатель
+    pub fn try_read<T: ?Sized>(lock: &RwLock<T>) -> Option<RwLockReadGuard<'_, T>> {
+        lock.try_read().ok()
+    }
+
+    pub fn try_write<T: ?Sized>(lock: &RwLock<T>) -> Option<RwLockWriteGuard<'_, T>> {
+        lock.try_write().ok()
+    }
+
+    // guard держит Arc блокировки, его можно отдать в задачу на другом потоке
+    pub async fn read_owned<T>(lock: &Arc<RwLock<T>>) -> OwnedReadGuard<T> {
+        lock.clone().read_owned().await
+    }
+
+    // задача на рабочих потоках рантайма; None, если задача запаниковала или рантайм её отменил
+    pub fn spawn<T: Send + 'static>(future: impl Future<Output = T> + Send + 'static) -> impl Future<Output = Option<T>> + Send {
+        let handle = tokio::spawn(future);
+
+        async move { handle.await.ok() }
+    }
+}
+
+#[cfg(all(feature = "async-lock", not(feature = "tokio")))]
+mod backend {
+    use std::{ops::{Deref, DerefMut}, ptr::NonNull, fmt::Debug, future::Future, panic::AssertUnwindSafe, sync::{Arc, OnceLock}};
+
+    use futures::{future::BoxFuture, executor::ThreadPool, FutureExt};
+
+    pub use async_lock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+    type Executor = Box<dyn Fn(BoxFuture<'static, ()>) + Send + Sync>;
+
+    static EXECUTOR: OnceLock<Executor> = OnceLock::new();
+
+    pub type OwnedReadGuard<T> = async_lock::RwLockReadGuardArc<T>;
+
+    // у guard'ов async-lock нет map: исходный guard держит блокировку в куче, а data указывает внутрь защищенного значения.
+    // значение лежит в самой блокировке, поэтому перенос guard'а указатель не портит
+    pub struct MappedReadGuard<'a, T: ?Sized> {
+        data: NonNull<T>,
+        _guard: Box<dyn Send + Sync + 'a>,
+    }
+
+    pub struct MappedWriteGuard<'a, T: ?Sized> {
+        data: NonNull<T>,
+        _guard: Box<dyn Send + Sync + 'a>,
+    }
+
+    // guard'ы выдают только ссылки на T, как и исходные guard'ы async-lock
+    unsafe impl<T: ?Sized + Sync> Send for MappedReadGuard<'_, T> {}
+    unsafe impl<T: ?Sized + Sync> Sync for MappedReadGuard<'_, T> {}
+    unsafe impl<T: ?Sized + Send + Sync> Send for MappedWriteGuard<'_, T> {}
+    unsafe impl<T: ?Sized + Send + Sync> Sync for MappedWriteGuard<'_, T> {}
+
+    pub fn map_read<'a, T, U>(guard: RwLockReadGuard<'a, T>, f: impl FnOnce(&T) -> &U) -> MappedReadGuard<'a, U>
+    where
+        T: ?Sized + Sync + 'a,
+        U: ?Sized,
+    {
+        MappedReadGuard {
+            data: NonNull::from(f(&*guard)),
+            _guard: Box::new(guard),
+        }
+    }
+
+    pub fn map_write<'a, T, U>(mut guard: RwLockWriteGuard<'a, T>, f: impl FnOnce(&mut T) -> &mut U) -> MappedWriteGuard<'a, U>
+    where
+        T: ?Sized + Send + Sync + 'a,
+        U: ?Sized,
+    {
+        MappedWriteGuard {
+            data: NonNull::from(f(&mut *guard)),
+            _guard: Box::new(guard),
+        }
+    }
+
+    // None, если блокировка занята несовместимо или её уже ждет писатель
+    pub fn try_read<T: ?Sized>(lock: &RwLock<T>) -> Option<RwLockReadGuard<'_, T>> {
+        lock.try_read()
+    }
+
+    pub fn try_write<T: ?Sized>(lock: &RwLock<T>) -> Option<RwLockWriteGuard<'_, T>> {
+        lock.try_write()
+    }
+
+    pub async fn read_owned<T>(lock: &Arc<RwLock<T>>) -> OwnedReadGuard<T> {
+        lock.read_arc().await
+    }
+
+    // исполнитель задач планировщика, например |task| smol::spawn(task).detach(); выбирается один раз, до первого кадра.
+    // false, если исполнитель уже выбран
+    pub fn set_executor(spawn: impl Fn(BoxFuture<'static, ()>) + Send + Sync + 'static) -> bool {
+        EXECUTOR.set(Box::new(spawn)).is_ok()
+    }
+
+    // своего рантайма у async-lock нет: без заданного исполнителя задачи идут в общий пул потоков по числу ядер,
+    // не меньше двух, чтобы независимые системы могли идти параллельно
+    fn executor() -> &'static Executor {
+        EXECUTOR.get_or_init(|| {
+            let pool_size = std::thread::available_parallelism().map_or(2, |threads| threads.get().max(2));
+            let pool = ThreadPool::builder().pool_size(pool_size).create().unwrap();
+
+            Box::new(move |task| pool.spawn_ok(task))
+        })
+    }
+
+    // None, если задача запаниковала или исполнитель её бросил
+    pub fn spawn<T: Send + 'static>(future: impl Future<Output = T> + Send + 'static) -> impl Future<Output = Option<T>> + Send {
+        let (sender, receiver) = futures::channel::oneshot::channel();
+
+        executor()(Box::pin(async move {
+            let _ = sender.send(AssertUnwindSafe(future).catch_unwind().await);
+        }));
+
+        async move { receiver.await.ok()?.ok() }
+    }
+
+    impl<T: ?Sized> Deref for MappedReadGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            unsafe { self.data.as_ref() }
+        }
+    }
+
+    impl<T: ?Sized> Deref for MappedWriteGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            unsafe { self.data.as_ref() }
+        }
+    }
+
+    impl<T: ?Sized> DerefMut for MappedWriteGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            unsafe { self.data.as_mut() }
+        }
+    }
+
+    impl<T: ?Sized + Debug> Debug for MappedReadGuard<'_, T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            (**self).fmt(f)
+        }
+    }
+
+    impl<T: ?Sized + Debug> Debug for MappedWriteGuard<'_, T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            (**self).fmt(f)
+        }
+    }
+}
+
+pub use backend::*;
@@ -1,11 +1,61 @@
+use std::{collections::BTreeSet, future::Future};
+
 use type_uuid::TypeUuid;
+use uuid::Uuid;
 
-use crate::world::World;
+use crate::{world::{World, IAccessManager, self}, commands::Commands};
 
 
 pub trait ISystem: TypeUuid + Sync + Send {
+    // колонки, которые система блокирует в query, по ним планировщик решает, что можно запускать параллельно
+    type TQuery: IAccessManager;
     type TProps<'frame>;
 
-    async fn query<'frame>(&mut self, world: &'frame World) -> Option<Self::TProps<'frame>>;
-    async fn system<'frame>(&mut self, props: Self::TProps<'frame>, world: &'frame World);
-}
\ No newline at end of file
+    // реализации пишутся как обычные async fn; Send нужен, чтобы планировщик мог запускать системы на разных потоках
+    fn query<'frame>(&mut self, world: &'frame World) -> impl Future<Output = Option<Self::TProps<'frame>>> + Send;
+    // структурные изменения мира записываются в commands и применяются после завершения системы
+    fn system<'frame>(&mut self, props: Self::TProps<'frame>, world: &'frame World, commands: &mut Commands) -> impl Future<Output = ()> + Send;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ComponentAccess {
+    pub reads: BTreeSet<Uuid>,
+    pub writes: BTreeSet<Uuid>,
+    // компоненты, без которых архетип не попадает в выборку системы
+    pub required: BTreeSet<Uuid>,
+    // ресурсы и каналы событий из reads и writes, они не делятся по архетипам
+    pub resources: BTreeSet<Uuid>,
+}
+
+pub fn access<TSystem: ISystem>() -> ComponentAccess {
+    TSystem::TQuery::component_access()
+}
+
+// колонки, которые одна из систем пишет, а другая читает или пишет
+pub fn conflicts(first: &ComponentAccess, second: &ComponentAccess) -> Vec<Uuid> {
+    let mut conflicts = first.writes.iter()
+        .filter(|uuid| second.reads.contains(uuid) || second.writes.contains(uuid))
+        .chain(second.writes.iter().filter(|uuid| first.reads.contains(uuid)))
+        .copied()
+        .collect::<Vec<_>>();
+
+    conflicts.sort();
+    conflicts.dedup();
+
+    conflicts
+}
+
+// конфликты, возможные в текущем мире: две системы блокируют одну колонку, только если есть архетип,
+// в котором есть эта колонка и обязательные компоненты обеих систем; по ресурсам конфликт есть всегда
+pub fn world_conflicts(first: &ComponentAccess, second: &ComponentAccess, world: &World) -> Vec<Uuid> {
+    conflicts(first, second).into_iter()
+        .filter(|uuid| {
+            first.resources.contains(uuid) ||
+            second.resources.contains(uuid) ||
+            world::has_archetype_with(world, first.required.iter()
+                .chain(second.required.iter())
+                .chain([uuid])
+                .copied())
+        })
+        .collect()
+}
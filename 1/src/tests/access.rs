@@ -0,0 +1,178 @@
+#[cfg(test)]
+pub mod access {
+    use std::any::TypeId;
+
+    use type_uuid::TypeUuid;
+
+    use crate::{world::{self, World, AccessError, Res}, entity::EntityId};
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "c4e1f0a2-5b6c-4d7e-8f90-a1b2c3d4e501"]
+    pub struct Position(pub u32);
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "c4e1f0a2-5b6c-4d7e-8f90-a1b2c3d4e502"]
+    pub struct Velocity(pub u32);
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "c4e1f0a2-5b6c-4d7e-8f90-a1b2c3d4e503"]
+    pub struct Player;
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "c4e1f0a2-5b6c-4d7e-8f90-a1b2c3d4e504"]
+    pub struct Bullet;
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "c4e1f0a2-5b6c-4d7e-8f90-a1b2c3d4e505"]
+    pub struct Gravity(pub u32);
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "c4e1f0a2-5b6c-4d7e-8f90-a1b2c3d4e506"]
+    pub struct Wind(pub u32);
+
+    fn is_contended<T: 'static, TAccess>(result: &Result<TAccess, AccessError>) -> bool {
+        matches!(result, Err(AccessError::Contended { component }) if component.id == TypeId::of::<T>())
+    }
+
+    fn is_missing<T: 'static, TAccess>(result: &Result<TAccess, AccessError>) -> bool {
+        matches!(result, Err(AccessError::Missing { component }) if component.id == TypeId::of::<T>())
+    }
+
+    fn is_duplicate<T: 'static, TAccess>(result: &Result<TAccess, AccessError>) -> bool {
+        matches!(result, Err(AccessError::Duplicate { component }) if component.id == TypeId::of::<T>())
+    }
+
+    async fn new_world() -> World {
+        let mut world = World::default();
+
+        world::add_entity(&mut world, (Position(0), Player)).await;
+        world::add_entity(&mut world, (Position(10), Bullet)).await;
+        world::insert_resource(&mut world, Gravity(1)).await;
+
+        world
+    }
+
+    #[tokio::test]
+    async fn try_get() {
+        let world = new_world().await;
+
+        // запись позиций игроков не мешает пулям: колонки Position у архетипов разные
+        let players = world::get::<(&mut Position, &Player)>(&world).await.unwrap();
+
+        assert!(world::try_get::<(&Position, &Bullet)>(&world).is_ok());
+        assert!(is_contended::<Position, _>(&world::try_get::<(&Position,)>(&world)));
+        assert!(is_contended::<Position, _>(&world::try_get::<(&Player, Option<&Position>)>(&world)));
+
+        drop(players);
+
+        // чтения совместимы между собой, но не с записью
+        let readers = world::get::<(&Position,)>(&world).await.unwrap();
+
+        assert!(world::try_get::<(&Position, &Player)>(&world).is_ok());
+        assert!(is_contended::<Position, _>(&world::try_get::<(&mut Position,)>(&world)));
+
+        drop(readers);
+
+        // одна колонка дважды не выдается, как и в get
+        assert!(is_duplicate::<Position, _>(&world::try_get::<(&mut Position, &mut Position)>(&world)));
+
+        assert!(is_missing::<Velocity, _>(&world::try_get::<(&Velocity,)>(&world)));
+        assert!(world::try_get::<(Option<&Velocity>, &mut Position)>(&world).is_ok());
+
+        let gravity = world::resource_mut::<Gravity>(&world).await.unwrap();
+
+        assert!(is_contended::<Gravity, _>(&world::try_get::<(&Position, Res<Gravity>)>(&world)));
+        assert!(is_missing::<Wind, _>(&world::try_get::<(Res<Wind>,)>(&world)));
+
+        drop(gravity);
+
+        let (positions, gravity) = world::try_get::<(&Position, Res<Gravity>)>(&world).unwrap();
+
+        assert_eq!((positions.len(), gravity.0), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn get_until() {
+        let world = new_world().await;
+
+        let writer = world::get::<(&mut Position,)>(&world).await.unwrap();
+
+        // срок уже вышел: get не дождался колонки, и последняя попытка называет её
+        let result = world::get_until::<(&Position, &Bullet)>(&world, std::future::ready(())).await;
+        assert!(is_contended::<Position, _>(&result));
+
+        drop(writer);
+
+        let result = world::get_until::<(&Position, &Bullet)>(&world, std::future::pending()).await;
+        assert_eq!(result.unwrap().0.len(), 1);
+
+        // недождавшийся get не оставил за собой блокировок
+        assert!(world::try_get::<(&mut Position,)>(&world).is_ok());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn get_timeout() {
+        use std::time::Duration;
+
+        let world = new_world().await;
+
+        let writer = world::get::<(&mut Position, &Player)>(&world).await.unwrap();
+
+        assert!(is_contended::<Position, _>(&world::get_timeout::<(&Position,)>(&world, Duration::from_millis(10)).await));
+        assert!(world::get_timeout::<(&Position, &Bullet)>(&world, Duration::from_millis(10)).await.is_ok());
+
+        drop(writer);
+
+        assert!(world::get_timeout::<(&Position,)>(&world, Duration::from_millis(10)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn entity_id_is_read_only() {
+        let world = new_world().await;
+
+        // выборка сама читает колонку EntityId: запись в нее ждала бы собственной блокировки
+        let query = tokio::time::timeout(std::time::Duration::from_secs(5), world::query::<(&mut EntityId,), ()>(&world)).await.unwrap();
+        assert!(query.is_none());
+
+        assert!(world::get::<(&mut EntityId, &Position)>(&world).await.is_none());
+        assert!(matches!(
+            world::try_get::<(&mut EntityId,)>(&world),
+            Err(AccessError::ReadOnly { component }) if component.id == TypeId::of::<EntityId>()
+        ));
+
+        let mut query = world::query::<(Option<&mut EntityId>, &Position), ()>(&world).await.unwrap();
+        assert!((&mut query).into_iter().all(|(entity_id, _)| entity_id.is_none()));
+        drop(query);
+
+        let mut query = world::query::<(&EntityId, &Position), ()>(&world).await.unwrap();
+        assert_eq!((&mut query).into_iter().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn duplicate_types() {
+        let world = new_world().await;
+
+        assert!(world::get::<(&Position, &Position)>(&world).await.is_none());
+        assert!(world::get::<(&mut Position, &Position)>(&world).await.is_none());
+        assert!(world::get::<(&Position, Option<&mut Position>)>(&world).await.is_none());
+
+        assert!(is_duplicate::<Position, _>(&world::try_get::<(&Position, &Position)>(&world)));
+        assert!(is_duplicate::<Position, _>(&world::try_get::<(&mut Position, &Position)>(&world)));
+        assert!(is_duplicate::<Position, _>(&world::try_get::<(&Position, Option<&mut Position>)>(&world)));
+
+        assert!(is_duplicate::<Position, _>(&world::get_until::<(&Position, &Position)>(&world, std::future::pending()).await));
+        assert!(is_duplicate::<Position, _>(&world::get_until::<(&mut Position, &Position)>(&world, std::future::pending()).await));
+
+        #[cfg(feature = "tokio")]
+        {
+            use std::time::Duration;
+
+            assert!(is_duplicate::<Position, _>(&world::get_timeout::<(&Position, &Position)>(&world, Duration::from_secs(5)).await));
+            assert!(is_duplicate::<Position, _>(&world::get_timeout::<(&mut Position, &Position)>(&world, Duration::from_secs(5)).await));
+        }
+
+        // отвергнутый набор не оставил блокировок
+        assert!(world::try_get::<(&mut Position,)>(&world).is_ok());
+    }
+}
@@ -1,11 +1,10 @@
 #[cfg(test)]
 pub mod base {
-    use tokio::sync::RwLock;
+    use crate::sync::RwLock;
     use futures::FutureExt;
-    use itertools::izip;
     use type_uuid::TypeUuid;
 
-    use crate::{archetype::{Archetype, self}, component, chunk, world::{self, World, WriteComponents, ReadComponents}, call, entity::EntityId, system::ISystem};
+    use crate::{world::{self, World, Query}, call, entity::EntityId, system::ISystem, commands::Commands};
 
     #[derive(Debug, TypeUuid)]
     #[uuid = "2ac0c046-bf65-4857-9095-0137d418521c"]
@@ -30,85 +29,30 @@ pub mod base {
     }
 
     pub struct MoveSystemProps<'system> {
-        pub archetypes: Vec<&'system Archetype>,
-        pub entity_id: ReadComponents<'system, EntityId>,
-        pub speed: ReadComponents<'system, Speed>,
-        pub position: WriteComponents<'system, Position>,
+        pub query: Query<'system, <MoveSystem as ISystem>::TQuery>,
     }
 
     impl ISystem for MoveSystem {
+        type TQuery = (&'static EntityId, &'static mut Position, &'static Speed);
         type TProps<'frame> = MoveSystemProps<'frame>;
 
         async fn query<'frame>(&mut self, world: &'frame World) -> Option<Self::TProps<'frame>> {
-            let archetypes_ids = world::query(world, |x| {
-                archetype::has::<Speed>(x) &&
-                archetype::has::<Position>(x)
-            });
-    
-            let archetypes =  world::archetypes(world, &archetypes_ids);
-    
-            if archetypes.len() == 0 {
-                return None;
-            }
-    
-            let (
-                entity_id,
-                position,
-                speed
-            ) = world::get::<(&EntityId, &mut Position, &Speed)>(world).await?;
-    
+            let query = world::query::<Self::TQuery, ()>(world).await?;
+
             Some(MoveSystemProps {
-                archetypes,
-                entity_id: entity_id,
-                speed: speed,
-                position: position,
+                query,
             })
         }
 
         async fn system<'frame>(&mut self, MoveSystemProps {
-            archetypes,
-            speed,
-            entity_id,
-            mut position,
-        }: Self::TProps<'frame>, _world: &'frame World) {
-            for archetype in archetypes {
-                let entity_id_chunk_ids = archetype::chunk_ids_by_type::<EntityId>(&archetype);
-                let speed_chunk_ids = archetype::chunk_ids_by_type::<Speed>(&archetype);
-                let position_chunk_ids = archetype::chunk_ids_by_type::<Position>(&archetype);
-        
-                let (
-                    Some(entity_id_chunk_ids),
-                    Some(speed_chunk_ids),
-                    Some(position_chunk_ids)
-                ) = (entity_id_chunk_ids, speed_chunk_ids, position_chunk_ids) else {
-                    continue;
-                };
-        
-                for (entity_id_chunk_id, speed_chunk_id, position_chunk_id) in izip!(entity_id_chunk_ids, speed_chunk_ids, position_chunk_ids) {
-                    let entity_id_chunk = component::chunk(&entity_id, *entity_id_chunk_id);
-                    let speed_chunk = component::chunk(&speed, *speed_chunk_id);
-                    let position_chunk = component::chunk_mut(&mut position, *position_chunk_id);
-        
-                    let (
-                        Some(entity_id_chunk),
-                        Some(speed_chunk),
-                        Some(position_chunk)
-                    ) = (entity_id_chunk, speed_chunk, position_chunk) else {
-                        continue;
-                    };
-        
-                    let entity_id_components = chunk::components(entity_id_chunk);
-                    let speed_components = chunk::components(speed_chunk);
-                    let position_components = chunk::components_mut(position_chunk);
-        
-                    for (entity_id, speed, position) in izip!(entity_id_components, speed_components, position_components) {
-                        println!("{entity_id:?}");
-    
-                        position.x += speed.x + self.offset;
-                        position.y += speed.y + self.offset;
-                        position.z += speed.z + self.offset;
-                    }
-                }
+            mut query,
+        }: Self::TProps<'frame>, _world: &'frame World, _commands: &mut Commands) {
+            for (entity_id, position, speed) in &mut query {
+                println!("{entity_id:?}");
+
+                position.x += speed.x + self.offset;
+                position.y += speed.y + self.offset;
+                position.z += speed.z + self.offset;
             }
         }
     }
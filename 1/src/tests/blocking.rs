@@ -0,0 +1,59 @@
+#[cfg(test)]
+pub mod blocking {
+    use type_uuid::TypeUuid;
+
+    use crate::world::{World, blocking};
+
+    #[derive(Debug, TypeUuid, PartialEq)]
+    #[uuid = "b70c4a1e-2d3f-4e56-8a7b-9c0d1e2f3a01"]
+    pub struct Position(pub u32);
+
+    #[derive(Debug, TypeUuid, PartialEq)]
+    #[uuid = "b70c4a1e-2d3f-4e56-8a7b-9c0d1e2f3a02"]
+    pub struct Speed(pub u32);
+
+    #[derive(Debug, TypeUuid, PartialEq)]
+    #[uuid = "b70c4a1e-2d3f-4e56-8a7b-9c0d1e2f3a03"]
+    pub struct Gravity(pub u32);
+
+    // обычный #[test]: асинхронного рантайма нет
+    #[test]
+    fn without_runtime() {
+        let mut world = World::default();
+
+        let moving = blocking::add_entity(&mut world, (Position(0), Speed(2)));
+        let resting = blocking::add_entity(&mut world, (Position(10),));
+
+        assert!(blocking::insert_component(&mut world, resting, Speed(1)));
+        assert_eq!(blocking::insert_resource(&mut world, Gravity(1)), None);
+
+        // обе сущности в одном архетипе, колонки выдаются по одной на архетип
+        let (positions, speeds) = blocking::get::<(&Position, &Speed)>(&world).unwrap();
+
+        assert_eq!((positions.len(), speeds.len()), (1, 1));
+
+        drop((positions, speeds));
+
+        let gravity = blocking::resource::<Gravity>(&world).unwrap().0;
+
+        {
+            let mut query = blocking::query::<(&mut Position, &Speed), ()>(&world).unwrap();
+
+            for (position, speed) in &mut query {
+                position.0 += speed.0 + gravity;
+            }
+        }
+
+        let mut query = blocking::query::<(&Position,), ()>(&world).unwrap();
+        let mut positions = (&mut query).into_iter().map(|(position,)| position.0).collect::<Vec<_>>();
+        positions.sort();
+
+        assert_eq!(positions, vec![3, 12]);
+
+        drop(query);
+
+        assert_eq!(blocking::remove_component::<Speed>(&mut world, moving), Some(Speed(2)));
+        assert!(blocking::remove_entity(&mut world, moving));
+        assert!(!blocking::remove_entity(&mut world, moving));
+    }
+}
@@ -0,0 +1,153 @@
+#[cfg(test)]
+pub mod commands {
+    use std::sync::Arc;
+
+    use crate::sync::RwLock;
+    use type_uuid::TypeUuid;
+
+    use crate::{archetype, world::{self, World}, entity::EntityId, system::ISystem, scheduler, call, commands::{Commands, self}};
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "c7e3a1f0-52b4-4d8e-9a61-0f2b3c4d5e01"]
+    pub struct Health(pub u32);
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "c7e3a1f0-52b4-4d8e-9a61-0f2b3c4d5e02"]
+    pub struct Dead;
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "c7e3a1f0-52b4-4d8e-9a61-0f2b3c4d5e03"]
+    pub struct Corpse;
+
+    // помечает сущности без здоровья мертвыми и заводит на их месте труп
+    #[derive(TypeUuid)]
+    #[uuid = "c7e3a1f0-52b4-4d8e-9a61-0f2b3c4d5e04"]
+    pub struct DeathSystem;
+
+    impl ISystem for DeathSystem {
+        type TQuery = (&'static EntityId, &'static Health);
+        type TProps<'frame> = Vec<EntityId>;
+
+        async fn query<'frame>(&mut self, world: &'frame World) -> Option<Self::TProps<'frame>> {
+            let mut query = world::query::<Self::TQuery, ()>(world).await?;
+
+            Some((&mut query).into_iter()
+                .filter(|(_, health)| health.0 == 0)
+                .map(|(entity_id, _)| *entity_id)
+                .collect())
+        }
+
+        async fn system<'frame>(&mut self, dead: Self::TProps<'frame>, world: &'frame World, commands: &mut Commands) {
+            for entity_id in dead {
+                commands::remove::<Health>(commands, entity_id);
+                commands::insert(commands, entity_id, Dead);
+                commands::spawn(commands, world, (Corpse,));
+            }
+        }
+    }
+
+    // удаляет мертвых, которых пометили на прошлом кадре
+    #[derive(TypeUuid)]
+    #[uuid = "c7e3a1f0-52b4-4d8e-9a61-0f2b3c4d5e05"]
+    pub struct CleanupSystem;
+
+    impl ISystem for CleanupSystem {
+        type TQuery = (&'static EntityId, &'static Dead);
+        type TProps<'frame> = Vec<EntityId>;
+
+        async fn query<'frame>(&mut self, world: &'frame World) -> Option<Self::TProps<'frame>> {
+            let mut query = world::query::<Self::TQuery, ()>(world).await?;
+
+            Some((&mut query).into_iter()
+                .map(|(entity_id, _)| *entity_id)
+                .collect())
+        }
+
+        async fn system<'frame>(&mut self, dead: Self::TProps<'frame>, _world: &'frame World, commands: &mut Commands) {
+            for entity_id in dead {
+                commands::despawn(commands, entity_id);
+            }
+        }
+    }
+
+    async fn count<TComponent: 'static + Sync + Send + TypeUuid + std::fmt::Debug>(world: &World) -> usize {
+        let Some(mut query) = world::query::<(&TComponent,), ()>(world).await else {
+            return 0;
+        };
+
+        (&mut query).into_iter().count()
+    }
+
+    #[tokio::test]
+    async fn deferred_commands() {
+        let world = Arc::new(RwLock::new(World::default()));
+
+        let (alive, dying) = {
+            let mut world = world.write().await;
+
+            (
+                world::add_entity(&mut world, (Health(10),)).await,
+                world::add_entity(&mut world, (Health(0),)).await,
+            )
+        };
+
+        let mut scheduler = scheduler::new();
+
+        scheduler::add_system(&mut scheduler, DeathSystem).unwrap();
+        scheduler::add_system(&mut scheduler, CleanupSystem).unwrap();
+
+        scheduler::run(&mut scheduler, &world).await.unwrap();
+
+        {
+            let world = world.read().await;
+
+            assert!(world::is_alive(&world, alive));
+            assert!(world::is_alive(&world, dying));
+            assert_eq!(count::<Health>(&world).await, 1);
+            assert_eq!(count::<Dead>(&world).await, 1);
+            assert_eq!(count::<Corpse>(&world).await, 1);
+        }
+
+        scheduler::run(&mut scheduler, &world).await.unwrap();
+
+        let world = world.read().await;
+
+        assert!(world::is_alive(&world, alive));
+        assert!(!world::is_alive(&world, dying));
+        assert_eq!(count::<Dead>(&world).await, 0);
+        assert_eq!(count::<Corpse>(&world).await, 1);
+    }
+
+    #[tokio::test]
+    async fn call_system_applies_commands() {
+        let world = Arc::new(RwLock::new(World::default()));
+
+        let dying = world::add_entity(&mut *world.write().await, (Health(0),)).await;
+
+        call::system(DeathSystem, world.clone()).await;
+
+        let world = world.read().await;
+
+        assert!(archetype::has::<Dead>(world::archetype(&world, world::location(&world, dying).unwrap().archetype).unwrap()));
+        assert_eq!(count::<Corpse>(&world).await, 1);
+    }
+
+    #[tokio::test]
+    async fn despawned_before_apply() {
+        let mut world = World::default();
+        let mut commands = commands::new();
+
+        let entity_id = commands::spawn(&mut commands, &world, (Health(1),));
+        commands::insert(&mut commands, entity_id, Dead);
+        commands::despawn(&mut commands, entity_id);
+        commands::insert(&mut commands, entity_id, Corpse);
+
+        assert_eq!(commands::len(&commands), 4);
+
+        commands::apply(&mut commands, &mut world).await;
+
+        assert!(commands::is_empty(&commands));
+        assert!(!world::is_alive(&world, entity_id));
+        assert_eq!(count::<Corpse>(&world).await, 0);
+    }
+}
@@ -0,0 +1,561 @@
+#[cfg(test)]
+pub mod entity {
+    use std::{collections::BTreeSet, sync::{Arc, atomic::{AtomicUsize, Ordering}}};
+
+    use type_uuid::TypeUuid;
+    use uuid::Uuid;
+
+    use crate::{archetype::{Archetype, ArchetypeId, EdgeKind, self}, registry, component, chunk::{ChunkSizing, self}, world::{self, World}, entity::{EntityId, GlobalId, self}, commands};
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "5b0f2b6e-4c1d-4a8e-9f57-3f1c2d0a9b11"]
+    pub struct Tracked {
+        pub value: u32,
+        pub drops: Arc<AtomicUsize>,
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    async fn tracked_values(world: &World) -> Vec<(EntityId, u32)> {
+        let Some(mut query) = world::query::<(&EntityId, &Tracked), ()>(world).await else {
+            return vec![];
+        };
+
+        (&mut query).into_iter()
+            .map(|(entity_id, tracked)| (*entity_id, tracked.value))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn remove_entity() {
+        let mut world = World::default();
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        let mut entity_ids = vec![];
+
+        for value in 0..40 {
+            let entity_id = world::add_entity(&mut world, (
+                Tracked { value, drops: drops.clone() },
+            )).await;
+
+            entity_ids.push((entity_id, value));
+        }
+
+        assert!(world::remove_entity(&mut world, entity_ids[3].0).await);
+        assert!(world::remove_entity(&mut world, entity_ids[39].0).await);
+        assert!(world::remove_entity(&mut world, entity_ids[35].0).await);
+        assert!(!world::remove_entity(&mut world, entity_ids[3].0).await);
+
+        assert_eq!(drops.load(Ordering::SeqCst), 3);
+
+        let mut values = tracked_values(&world).await;
+        values.sort_by_key(|(_, value)| *value);
+
+        let mut expected = entity_ids.clone();
+        expected.retain(|(_, value)| ![3, 35, 39].contains(value));
+
+        assert_eq!(values, expected);
+
+        let entity_id = world::add_entity(&mut world, (
+            Tracked { value: 100, drops: drops.clone() },
+        )).await;
+
+        assert!(tracked_values(&world).await.contains(&(entity_id, 100)));
+    }
+
+    fn archetype_of(world: &World, entity_id: EntityId) -> &Archetype {
+        world::archetype(world, world::location(world, entity_id).unwrap().archetype).unwrap()
+    }
+
+    async fn entity_at(world: &World, location: &world::EntityLocation) -> Option<EntityId> {
+        let archetype = world::archetype(world, location.archetype)?;
+        let chunk_ids = archetype::chunk_ids_by_type::<EntityId>(archetype)?;
+
+        let entity_ids = world::read_column::<EntityId>(archetype).await?;
+        let chunk = component::chunk(&entity_ids, *chunk_ids.get(location.chunk)?)?;
+
+        chunk::components(chunk).get(location.row).copied()
+    }
+
+    #[tokio::test]
+    async fn entity_location() {
+        let mut world = World::default();
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        let mut entity_ids = vec![];
+
+        for value in 0..70 {
+            entity_ids.push(world::add_entity(&mut world, (
+                Tracked { value, drops: drops.clone() },
+            )).await);
+        }
+
+        for entity_id in entity_ids.drain(10..20) {
+            assert!(world::remove_entity(&mut world, entity_id).await);
+            assert!(!world::is_alive(&world, entity_id));
+            assert!(world::location(&world, entity_id).is_none());
+        }
+
+        for entity_id in entity_ids {
+            assert!(world::is_alive(&world, entity_id));
+
+            let location = world::location(&world, entity_id).unwrap();
+
+            assert_eq!(entity_at(&world, location).await, Some(entity_id));
+        }
+    }
+
+    #[derive(Debug, TypeUuid, PartialEq)]
+    #[uuid = "8d7c6f3a-1e2b-4c5d-9a8b-7c6d5e4f3a21"]
+    pub struct Stunned {
+        pub turns: u32,
+    }
+
+    #[tokio::test]
+    async fn archetype_migration() {
+        let mut world = World::default();
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        let mut entity_ids = vec![];
+
+        for value in 0..40 {
+            entity_ids.push(world::add_entity(&mut world, (
+                Tracked { value, drops: drops.clone() },
+            )).await);
+        }
+
+        for entity_id in entity_ids.iter().step_by(3) {
+            assert!(world::insert_component(&mut world, *entity_id, Stunned { turns: 1 }).await);
+        }
+
+        assert!(world::insert_component(&mut world, entity_ids[0], Stunned { turns: 5 }).await);
+
+        // перенос строки между архетипами не должен дропать компоненты
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+
+        let mut values = tracked_values(&world).await;
+        values.sort_by_key(|(_, value)| *value);
+
+        assert_eq!(values.iter().map(|(entity_id, _)| *entity_id).collect::<Vec<_>>(), entity_ids);
+
+        for (idx, entity_id) in entity_ids.iter().enumerate() {
+            let location = world::location(&world, *entity_id).unwrap();
+
+            assert_eq!(archetype::has::<Stunned>(archetype_of(&world, *entity_id)), idx % 3 == 0);
+            assert_eq!(entity_at(&world, location).await, Some(*entity_id));
+        }
+
+        assert_eq!(world::remove_component::<Stunned>(&mut world, entity_ids[0]).await, Some(Stunned { turns: 5 }));
+        assert_eq!(world::remove_component::<Stunned>(&mut world, entity_ids[0]).await, None);
+        assert_eq!(world::remove_component::<Stunned>(&mut world, entity_ids[1]).await, None);
+        assert_eq!(world::remove_component::<Stunned>(&mut world, entity_ids[3]).await, Some(Stunned { turns: 1 }));
+
+        assert!(world::remove_component::<Tracked>(&mut world, entity_ids[6]).await.is_some());
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert!(!tracked_values(&world).await.iter().any(|(entity_id, _)| *entity_id == entity_ids[6]));
+
+        for entity_id in entity_ids {
+            let location = world::location(&world, entity_id).unwrap();
+
+            assert_eq!(entity_at(&world, location).await, Some(entity_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicate_components() {
+        let mut world = World::default();
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        // повтор не рядом с первым вхождением: остается первый, колонки архетипа не разъезжаются
+        let first = world::add_entity(&mut world, (
+            Stunned { turns: 1 },
+            Tracked { value: 1, drops: drops.clone() },
+            Stunned { turns: 100 },
+        )).await;
+
+        let second = world::add_entity(&mut world, (
+            Stunned { turns: 2 },
+            Tracked { value: 2, drops: drops.clone() },
+        )).await;
+
+        assert_eq!(world::location(&world, first).unwrap().archetype, world::location(&world, second).unwrap().archetype);
+
+        let mut query = world::query::<(&EntityId, &Tracked, &Stunned), ()>(&world).await.unwrap();
+        let mut rows = (&mut query).into_iter()
+            .map(|(entity_id, tracked, stunned)| (*entity_id, tracked.value, stunned.turns))
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|(_, value, _)| *value);
+
+        assert_eq!(rows, vec![(first, 1, 1), (second, 2, 2)]);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn generational_ids() {
+        let mut world = World::default();
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        let first = world::add_entity(&mut world, (Tracked { value: 1, drops: drops.clone() },)).await;
+        let second = world::add_entity(&mut world, (Tracked { value: 2, drops: drops.clone() },)).await;
+
+        assert_eq!((entity::index(first), entity::generation(first)), (0, 0));
+        assert_eq!((entity::index(second), entity::generation(second)), (1, 0));
+
+        assert!(world::remove_entity(&mut world, first).await);
+
+        // слот переиспользуется, но со следующим поколением, старый идентификатор остается мертвым
+        let reused = world::add_entity(&mut world, (Tracked { value: 3, drops: drops.clone() },)).await;
+
+        assert_eq!((entity::index(reused), entity::generation(reused)), (0, 1));
+        assert!(!world::is_alive(&world, first));
+        assert!(world::is_alive(&world, reused));
+        assert!(!world::remove_entity(&mut world, first).await);
+        assert!(!world::insert_component(&mut world, first, Stunned { turns: 1 }).await);
+        assert!(world::is_alive(&world, reused));
+
+        // зарезервированный через &World идентификатор не пересекается с выданными позже
+        let reserved = world::reserve_entity(&world);
+        let allocated = world::add_entity(&mut world, (Tracked { value: 4, drops: drops.clone() },)).await;
+
+        assert_ne!(entity::index(reserved), entity::index(allocated));
+        assert!(!world::is_alive(&world, reserved));
+
+        assert!(world::add_entity_with_id(&mut world, reserved, (
+            Tracked { value: 5, drops: drops.clone() },
+            entity::new_global_id(),
+        )).await);
+        assert!(!world::add_entity_with_id(&mut world, reserved, ()).await);
+
+        let mut values = tracked_values(&world).await;
+        values.sort_by_key(|(_, value)| *value);
+
+        assert_eq!(values, vec![(second, 2), (reused, 3), (allocated, 4), (reserved, 5)]);
+
+        // у каждого архетипа своя колонка, GlobalId есть только у сущности reserved
+        let (global_ids,) = world::get::<(&GlobalId,)>(&world).await.unwrap();
+        assert_eq!(global_ids.len(), 1);
+        assert_eq!(component::chunk(global_ids[0].as_ref().unwrap(), 0).map(chunk::len), Some(1));
+    }
+
+    #[tokio::test]
+    async fn spawn_batch_after_place() {
+        let mut world = World::default();
+
+        let placed = entity::from_raw(0, 0);
+
+        assert!(world::add_entity_with_id(&mut world, placed, (Stunned { turns: 0 },)).await);
+        assert!(world::remove_entity(&mut world, placed).await);
+
+        let entity_ids = world::spawn_batch(&mut world, (1..3).map(|turns| (Stunned { turns },))).await.unwrap();
+
+        assert_ne!(entity_ids[0], entity_ids[1]);
+
+        for entity_id in entity_ids.iter() {
+            let location = world::location(&world, *entity_id).unwrap();
+
+            assert_eq!(entity_at(&world, location).await, Some(*entity_id));
+        }
+    }
+
+    // обобщенный набор: future spawn_batch должен быть Send без знания конкретного типа
+    fn spawn_batch_in_task<TBundle: world::IBundle + Send + 'static>(mut world: World, bundles: Vec<TBundle>) -> tokio::task::JoinHandle<(World, Option<Vec<EntityId>>)> {
+        tokio::spawn(async move {
+            let entity_ids = world::spawn_batch(&mut world, bundles).await;
+            (world, entity_ids)
+        })
+    }
+
+    #[tokio::test]
+    async fn spawn_batch_in_spawned_task() {
+        let (world, entity_ids) = spawn_batch_in_task(World::default(), vec![(Stunned { turns: 1 },), (Stunned { turns: 2 },)]).await.unwrap();
+
+        assert!(entity_ids.unwrap().iter().all(|entity_id| world::is_alive(&world, *entity_id)));
+    }
+
+    #[test]
+    fn place_past_end() {
+        let mut entities = entity::Entities::default();
+
+        let placed = entity::from_raw(3, 0);
+
+        assert!(entity::place(&mut entities, placed, world::EntityLocation::default()));
+        assert!(entity::free(&mut entities, placed).is_some());
+
+        // занятый через place слот попадает в список свободных только при free, один раз
+        let first = entity::alloc(&mut entities);
+        let second = entity::alloc(&mut entities);
+
+        assert_eq!(first, entity::from_raw(3, 1));
+        assert_ne!(entity::index(first), entity::index(second));
+    }
+
+    #[tokio::test]
+    async fn spawn_batch() {
+        let mut world = World::default();
+        let drops = Arc::new(AtomicUsize::new(0));
+
+        // архетип уже частично заполнен, пачка должна сначала дописать последний чанк
+        let mut entity_ids = vec![];
+
+        for value in 0..10 {
+            entity_ids.push(world::add_entity(&mut world, (Tracked { value, drops: drops.clone() }, Stunned { turns: value })).await);
+        }
+
+        entity_ids.extend(world::spawn_batch(&mut world, (10..100).map(|value| (
+            Stunned { turns: value },
+            Tracked { value, drops: drops.clone() },
+        ))).await.unwrap());
+
+        assert_eq!(entity_ids.len(), 100);
+
+        let mut values = tracked_values(&world).await;
+        values.sort_by_key(|(_, value)| *value);
+
+        assert_eq!(values, entity_ids.iter().copied().zip(0..100).collect::<Vec<_>>());
+
+        for entity_id in entity_ids.iter() {
+            let location = world::location(&world, *entity_id).unwrap();
+
+            assert_eq!(entity_at(&world, location).await, Some(*entity_id));
+        }
+
+        {
+            let mut query = world::query::<(&Tracked, &Stunned), ()>(&world).await.unwrap();
+            assert!((&mut query).into_iter().all(|(tracked, stunned)| tracked.value == stunned.turns));
+        }
+
+        // обычные структурные изменения продолжают работать поверх строк пачки
+        assert!(world::remove_entity(&mut world, entity_ids[50]).await);
+        assert!(world::remove_entity(&mut world, entity_ids[3]).await);
+
+        for entity_id in entity_ids.iter().filter(|entity_id| world::is_alive(&world, **entity_id)) {
+            let location = world::location(&world, *entity_id).unwrap();
+
+            assert_eq!(entity_at(&world, location).await, Some(*entity_id));
+        }
+
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+
+        assert!(world::spawn_batch(&mut world, [(Stunned { turns: 0 }, Stunned { turns: 1 })]).await.is_none());
+        assert!(world::spawn_batch(&mut world, [(entity::from_raw(0, 0),)]).await.is_none());
+        assert_eq!(world::spawn_batch(&mut world, Vec::<(Stunned,)>::new()).await, Some(vec![]));
+    }
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "3f2e1d0c-9b8a-4765-8432-10fedcba9801"]
+    pub struct Blob(pub [u8; 64]);
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "3f2e1d0c-9b8a-4765-8432-10fedcba9802"]
+    pub struct Tag;
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "3f2e1d0c-9b8a-4765-8432-10fedcba9803"]
+    pub struct Marker;
+
+    fn chunk_capacity(world: &World, entity_id: EntityId) -> (usize, usize) {
+        let location = world::location(world, entity_id).unwrap();
+        let archetype = world::archetype(world, location.archetype).unwrap();
+
+        (archetype::chunk_capacity(archetype), archetype::chunk_ids_by_type::<EntityId>(archetype).unwrap().len())
+    }
+
+    #[tokio::test]
+    async fn chunk_sizing() {
+        let mut world = World::default();
+
+        world::set_chunk_sizing(&mut world, ChunkSizing::Bytes(256));
+        world::set_component_chunk_sizing::<Marker>(&mut world, ChunkSizing::Rows(3));
+
+        let blobs = world::spawn_batch(&mut world, (0..10).map(|_| (Blob([0; 64]),))).await.unwrap();
+
+        // 256 / 64 = 4 строки, EntityId поместился бы и по 32
+        assert_eq!(chunk_capacity(&world, blobs[0]), (4, 3));
+
+        let mut tags = vec![];
+
+        for _ in 0..40 {
+            tags.push(world::add_entity(&mut world, (Tag,)).await);
+        }
+
+        // компонент нулевого размера не растягивает чанк, вместимость задает EntityId
+        assert_eq!(chunk_capacity(&world, tags[0]), (32, 2));
+
+        let markers = world::spawn_batch(&mut world, (0..7).map(|_| (Marker, Tag))).await.unwrap();
+
+        assert_eq!(chunk_capacity(&world, markers[0]), (3, 3));
+
+        for entity_id in blobs.iter().chain(tags.iter()).chain(markers.iter()) {
+            let location = world::location(&world, *entity_id).unwrap();
+            let archetype = world::archetype(&world, location.archetype).unwrap();
+
+            let entity_ids = world::read_column::<EntityId>(archetype).await.unwrap();
+            let chunk = component::chunk(&entity_ids, archetype::chunk_ids_by_type::<EntityId>(archetype).unwrap()[location.chunk]).unwrap();
+
+            assert_eq!(chunk::components(chunk)[location.row], *entity_id);
+        }
+
+        // все колонки архетипа разбиты на чанки одинаково
+        for entity_id in tags.drain(..).step_by(2) {
+            assert!(world::insert_component(&mut world, entity_id, Marker).await);
+        }
+
+        let mut query = world::query::<(&EntityId, &Marker, &Tag), ()>(&world).await.unwrap();
+        assert_eq!((&mut query).into_iter().count(), 27);
+    }
+
+    #[tokio::test]
+    async fn abandoned_reservations() {
+        let mut world = World::default();
+
+        let mut dropped = commands::new();
+        let abandoned = commands::spawn(&mut dropped, &world, (Marker,));
+        commands::insert(&mut dropped, abandoned, Tag);
+        drop(dropped);
+
+        // слот брошенного резерва снова выдается, но старый идентификатор мертв
+        let reused = world::add_entity(&mut world, (Marker,)).await;
+
+        assert_eq!(entity::index(reused), entity::index(abandoned));
+        assert_eq!(entity::generation(reused), entity::generation(abandoned) + 1);
+        assert!(!world::is_alive(&world, abandoned));
+        assert!(!world::insert_component(&mut world, abandoned, Tag).await);
+
+        // резерв еще не примененного буфера не выдается другим, даже когда мир меняется раньше применения
+        let mut pending = commands::new();
+        let deferred = commands::spawn(&mut pending, &world, (Marker,));
+
+        let allocated = world::add_entity(&mut world, (Marker,)).await;
+        assert_ne!(entity::index(allocated), entity::index(deferred));
+
+        commands::apply(&mut pending, &mut world).await;
+
+        assert!(world::is_alive(&world, deferred));
+        assert!(world::is_alive(&world, allocated));
+        assert!(world::is_alive(&world, reused));
+
+        let mut query = world::query::<(&EntityId, &Marker), ()>(&world).await.unwrap();
+        assert_eq!((&mut query).into_iter().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn empty_entities() {
+        let mut world = World::default();
+
+        assert!(world::empty_archetype(&world).is_none());
+
+        let empty = world::spawn_empty(&mut world).await;
+
+        let reserved = world::reserve_entity(&world);
+        assert!(world::add_entity_with_id(&mut world, reserved, ()).await);
+
+        let mut commands = commands::new();
+        let deferred = commands::spawn_empty(&mut commands, &world);
+        commands::insert(&mut commands, deferred, Tag);
+        commands::apply(&mut commands, &mut world).await;
+
+        let empty_archetype = archetype::id(world::empty_archetype(&world).unwrap());
+
+        assert!(archetype::is_empty(archetype_of(&world, empty)));
+        assert_eq!(world::location(&world, empty).unwrap().archetype, empty_archetype);
+        assert_eq!(world::location(&world, reserved).unwrap().archetype, empty_archetype);
+        assert!(archetype::has::<Tag>(archetype_of(&world, deferred)));
+
+        let mut query = world::query::<(&EntityId,), ()>(&world).await.unwrap();
+        assert_eq!((&mut query).into_iter().count(), 3);
+        drop(query);
+
+        // компоненты навешиваются позже, сущность уходит из пустого архетипа и возвращается в него
+        assert!(world::insert_component(&mut world, empty, Marker).await);
+        assert!(!archetype::is_empty(archetype_of(&world, empty)));
+
+        assert!(world::remove_component::<Marker>(&mut world, empty).await.is_some());
+        assert_eq!(world::location(&world, empty).unwrap().archetype, empty_archetype);
+
+        assert!(world::remove_entity(&mut world, reserved).await);
+        assert!(world::is_alive(&world, empty));
+    }
+
+    #[tokio::test]
+    async fn archetype_graph() {
+        let mut world = World::default();
+
+        let first = world::add_entity(&mut world, (Tag,)).await;
+        let second = world::add_entity(&mut world, (Tag,)).await;
+
+        let tagged = world::location(&world, first).unwrap().archetype;
+
+        assert!(world::archetype_graph(&world).iter().all(|(_, edges)| edges.is_empty()));
+
+        assert!(world::insert_component(&mut world, first, Marker).await);
+
+        let marked = world::location(&world, first).unwrap().archetype;
+
+        // второй переезд идет по закешированному ребру и попадает в тот же архетип
+        assert!(world::insert_component(&mut world, second, Marker).await);
+        assert_eq!(world::location(&world, second).unwrap().archetype, marked);
+
+        assert!(world::remove_component::<Tag>(&mut world, first).await.is_some());
+
+        let untagged = world::location(&world, first).unwrap().archetype;
+
+        let tag = world::component_id(&world, Uuid::from_bytes(Tag::UUID)).unwrap();
+        let marker = world::component_id(&world, Uuid::from_bytes(Marker::UUID)).unwrap();
+
+        let graph = world::archetype_graph(&world);
+        let edges = |archetype_id: ArchetypeId| graph.iter()
+            .find(|(id, _)| *id == archetype_id)
+            .map(|(_, edges)| edges.iter().map(|edge| (edge.kind, edge.component, edge.target)).collect::<Vec<_>>())
+            .unwrap();
+
+        assert_eq!(edges(tagged), vec![(EdgeKind::Add, marker, marked)]);
+
+        let mut expected = vec![(EdgeKind::Remove, marker, tagged), (EdgeKind::Remove, tag, untagged)];
+        expected.sort_by_key(|(kind, component, _)| (*kind, *component));
+
+        assert_eq!(edges(marked), expected);
+        assert_eq!(edges(untagged), vec![(EdgeKind::Add, tag, marked)]);
+
+        let mut query = world::query::<(&EntityId, &Marker), ()>(&world).await.unwrap();
+        assert_eq!((&mut query).into_iter().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn dense_ids() {
+        let mut world = World::default();
+
+        let blob = world::add_entity(&mut world, (Blob([1; 64]),)).await;
+        let tagged_blob = world::add_entity(&mut world, (Blob([2; 64]), Tag)).await;
+
+        let blob_id = world::component_id(&world, Uuid::from_bytes(Blob::UUID)).unwrap();
+        let tag_id = world::component_id(&world, Uuid::from_bytes(Tag::UUID)).unwrap();
+
+        assert_eq!(world::component_uuid(&world, tag_id), Some(Uuid::from_bytes(Tag::UUID)));
+        assert!(world::component_id(&world, Uuid::from_bytes(Marker::UUID)).is_none());
+
+        let key = [Uuid::from_bytes(Blob::UUID), Uuid::from_bytes(Tag::UUID), Uuid::from_bytes(EntityId::UUID)].into_iter().collect::<BTreeSet<_>>();
+        let archetype_id = world::archetype_id(&world, &key).unwrap();
+
+        assert_eq!(world::location(&world, tagged_blob).unwrap().archetype, archetype_id);
+        assert_eq!(archetype::components(archetype_of(&world, tagged_blob)), &key);
+
+        let signature = archetype::signature(archetype_of(&world, tagged_blob));
+
+        assert!(registry::contains(signature, blob_id) && registry::contains(signature, tag_id));
+        assert!(registry::is_superset(signature, archetype::signature(archetype_of(&world, blob))));
+        assert!(!registry::is_superset(archetype::signature(archetype_of(&world, blob)), signature));
+        assert_eq!(registry::ids(signature).count(), 3);
+
+        // компонент, которого мир еще не видел, не совпадает ни с одним архетипом
+        let mut query = world::query::<(&EntityId, &Marker), ()>(&world).await;
+        assert!(query.as_mut().is_none_or(|query| query.into_iter().count() == 0));
+        drop(query);
+
+        let mut query = world::query::<(&EntityId, &Blob), ()>(&world).await.unwrap();
+        assert_eq!((&mut query).into_iter().count(), 2);
+    }
+}
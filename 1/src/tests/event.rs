@@ -0,0 +1,206 @@
+#[cfg(test)]
+pub mod event {
+    use std::sync::{Arc, Mutex};
+
+    use crate::sync::RwLock;
+    use type_uuid::TypeUuid;
+
+    use crate::{world::{self, World, EventReader, EventWriter}, entity::EntityId, system::ISystem, scheduler, commands::{Commands, self}, event::{EventCursor, self}};
+
+    #[derive(Debug, TypeUuid, Clone, Copy, PartialEq)]
+    #[uuid = "f4a1b2c3-d4e5-4f60-8172-839405a6b701"]
+    pub struct Collision(pub EntityId);
+
+    #[derive(Debug, TypeUuid, Clone, Copy, PartialEq)]
+    #[uuid = "f4a1b2c3-d4e5-4f60-8172-839405a6b702"]
+    pub struct Damage {
+        pub entity_id: EntityId,
+        pub amount: u32,
+    }
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "f4a1b2c3-d4e5-4f60-8172-839405a6b703"]
+    pub struct Health(pub u32);
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "f4a1b2c3-d4e5-4f60-8172-839405a6b704"]
+    pub struct Message(pub u32);
+
+    #[derive(TypeUuid)]
+    #[uuid = "f4a1b2c3-d4e5-4f60-8172-839405a6b705"]
+    pub struct CollisionSystem {
+        pub pending: Arc<Mutex<Vec<EntityId>>>,
+    }
+
+    impl ISystem for CollisionSystem {
+        type TQuery = (EventWriter<Collision>,);
+        type TProps<'frame> = ();
+
+        async fn query<'frame>(&mut self, _world: &'frame World) -> Option<Self::TProps<'frame>> {
+            Some(())
+        }
+
+        async fn system<'frame>(&mut self, _props: Self::TProps<'frame>, world: &'frame World, _commands: &mut Commands) {
+            let (mut collisions,) = world::get::<Self::TQuery>(world).await.unwrap();
+
+            for entity_id in self.pending.lock().unwrap().drain(..) {
+                event::send(&mut collisions, Collision(entity_id));
+            }
+        }
+    }
+
+    #[derive(TypeUuid)]
+    #[uuid = "f4a1b2c3-d4e5-4f60-8172-839405a6b706"]
+    pub struct DamageSystem {
+        pub collisions: EventCursor<Collision>,
+    }
+
+    impl ISystem for DamageSystem {
+        type TQuery = (EventReader<Collision>, EventWriter<Damage>);
+        type TProps<'frame> = ();
+
+        async fn query<'frame>(&mut self, _world: &'frame World) -> Option<Self::TProps<'frame>> {
+            Some(())
+        }
+
+        async fn system<'frame>(&mut self, _props: Self::TProps<'frame>, world: &'frame World, _commands: &mut Commands) {
+            let (collisions, mut damage) = world::get::<Self::TQuery>(world).await.unwrap();
+
+            for Collision(entity_id) in event::read(&collisions, &mut self.collisions) {
+                event::send(&mut damage, Damage { entity_id: *entity_id, amount: 10 });
+            }
+        }
+    }
+
+    #[derive(TypeUuid)]
+    #[uuid = "f4a1b2c3-d4e5-4f60-8172-839405a6b707"]
+    pub struct HealthSystem {
+        pub damage: EventCursor<Damage>,
+    }
+
+    impl ISystem for HealthSystem {
+        type TQuery = (&'static EntityId, &'static mut Health, EventReader<Damage>);
+        type TProps<'frame> = ();
+
+        async fn query<'frame>(&mut self, _world: &'frame World) -> Option<Self::TProps<'frame>> {
+            Some(())
+        }
+
+        async fn system<'frame>(&mut self, _props: Self::TProps<'frame>, world: &'frame World, commands: &mut Commands) {
+            let damage = {
+                let damage = world::events::<Damage>(world).await.unwrap();
+                event::read(&damage, &mut self.damage).copied().collect::<Vec<_>>()
+            };
+
+            let Some(mut query) = world::query::<(&EntityId, &mut Health), ()>(world).await else {
+                return;
+            };
+
+            for (entity_id, health) in &mut query {
+                for Damage { amount, .. } in damage.iter().filter(|damage| damage.entity_id == *entity_id) {
+                    health.0 = health.0.saturating_sub(*amount);
+                }
+
+                if health.0 == 0 {
+                    commands::despawn(commands, *entity_id);
+                }
+            }
+        }
+    }
+
+    async fn health(world: &World, entity_id: EntityId) -> Option<u32> {
+        let mut query = world::query::<(&EntityId, &Health), ()>(world).await?;
+
+        (&mut query).into_iter()
+            .find(|(id, _)| **id == entity_id)
+            .map(|(_, health)| health.0)
+    }
+
+    #[tokio::test]
+    async fn event_chain_in_one_frame() {
+        let world = Arc::new(RwLock::new(World::default()));
+
+        let (target, bystander) = {
+            let mut world = world.write().await;
+
+            world::register_event::<Collision>(&mut world);
+            world::register_event::<Damage>(&mut world);
+
+            (
+                world::add_entity(&mut world, (Health(20),)).await,
+                world::add_entity(&mut world, (Health(20),)).await,
+            )
+        };
+
+        let pending = Arc::new(Mutex::new(vec![]));
+
+        let mut scheduler = scheduler::new();
+
+        scheduler::add_system(&mut scheduler, CollisionSystem { pending: pending.clone() }).unwrap();
+        scheduler::add_system(&mut scheduler, DamageSystem { collisions: event::cursor() }).unwrap();
+        scheduler::add_system(&mut scheduler, HealthSystem { damage: event::cursor() }).unwrap();
+
+        scheduler::before::<CollisionSystem, DamageSystem>(&mut scheduler);
+        scheduler::before::<DamageSystem, HealthSystem>(&mut scheduler);
+
+        pending.lock().unwrap().push(target);
+        scheduler::run(&mut scheduler, &world).await.unwrap();
+
+        assert_eq!(health(&*world.read().await, target).await, Some(10));
+
+        // события прошлого кадра еще видны, но курсоры уже прошли их, так что урон не повторяется
+        scheduler::run(&mut scheduler, &world).await.unwrap();
+
+        assert_eq!(health(&*world.read().await, target).await, Some(10));
+
+        pending.lock().unwrap().push(target);
+        scheduler::run(&mut scheduler, &world).await.unwrap();
+
+        let world = world.read().await;
+
+        assert!(!world::is_alive(&world, target));
+        assert!(world::is_alive(&world, bystander));
+    }
+
+    #[tokio::test]
+    async fn double_buffering() {
+        let mut world = World::default();
+
+        assert!(!world::send_event(&world, Message(0)).await);
+
+        world::register_event::<Message>(&mut world);
+
+        let mut early = event::cursor::<Message>();
+        let mut late = event::cursor::<Message>();
+
+        let read = async |world: &World, cursor: &mut EventCursor<Message>| {
+            let events = world::events::<Message>(world).await.unwrap();
+            event::read(&events, cursor).map(|message| message.0).collect::<Vec<_>>()
+        };
+
+        assert!(world::send_event(&world, Message(1)).await);
+        assert!(world::send_event(&world, Message(2)).await);
+
+        assert_eq!(read(&world, &mut early).await, vec![1, 2]);
+
+        world::update_events(&world).await;
+
+        world::send_event(&world, Message(3)).await;
+
+        assert_eq!(read(&world, &mut early).await, vec![3]);
+        assert_eq!(read(&world, &mut early).await, Vec::<u32>::new());
+
+        // читатель, пропустивший кадр, видит и прошлый, и текущий кадр
+        assert_eq!(read(&world, &mut late).await, vec![1, 2, 3]);
+
+        world::update_events(&world).await;
+        world::update_events(&world).await;
+
+        world::send_event(&world, Message(4)).await;
+
+        let mut fresh = event::cursor::<Message>();
+
+        assert_eq!(read(&world, &mut fresh).await, vec![4]);
+        assert_eq!(event::len(&*world::events::<Message>(&world).await.unwrap()), 1);
+    }
+}
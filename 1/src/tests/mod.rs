@@ -1 +1,10 @@
 pub mod base;
+pub mod entity;
+pub mod query;
+pub mod scheduler;
+pub mod commands;
+pub mod resource;
+pub mod event;
+pub mod snapshot;
+pub mod blocking;
+pub mod access;
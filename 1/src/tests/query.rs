@@ -0,0 +1,386 @@
+#[cfg(test)]
+pub mod query {
+    use type_uuid::TypeUuid;
+    use uuid::Uuid;
+
+    use crate::{world::{self, World, IQueryFilter, With, Without, Not, Or, Added, Changed}, entity::EntityId};
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "0f4e5d6c-7b8a-4c9d-8e1f-2a3b4c5d6e01"]
+    pub struct Position {
+        pub x: u32,
+    }
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "0f4e5d6c-7b8a-4c9d-8e1f-2a3b4c5d6e02"]
+    pub struct Speed {
+        pub x: u32,
+    }
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "0f4e5d6c-7b8a-4c9d-8e1f-2a3b4c5d6e03"]
+    pub struct Player;
+
+    #[tokio::test]
+    async fn query_iter() {
+        let mut world = World::default();
+
+        for x in 0..50 {
+            world::add_entity(&mut world, (Position { x }, Speed { x: 1 })).await;
+        }
+
+        for x in 0..10 {
+            world::add_entity(&mut world, (Position { x }, Speed { x: 2 }, Player)).await;
+        }
+
+        for x in 0..5 {
+            world::add_entity(&mut world, (Position { x },)).await;
+        }
+
+        {
+            let mut query = world::query::<(&EntityId, &mut Position, &Speed), ()>(&world).await.unwrap();
+
+            let mut count = 0;
+
+            for (_entity_id, position, speed) in &mut query {
+                position.x += speed.x * 100;
+                count += 1;
+            }
+
+            assert_eq!(count, 60);
+        }
+
+        let mut query = world::query::<(&Position, &EntityId), ()>(&world).await.unwrap();
+
+        let mut moved = (&mut query).into_iter()
+            .filter(|(position, _)| position.x >= 100)
+            .map(|(position, _)| position.x)
+            .collect::<Vec<_>>();
+
+        moved.sort();
+
+        let mut expected = (0..50).map(|x| x + 100).chain((0..10).map(|x| x + 200)).collect::<Vec<_>>();
+        expected.sort();
+
+        assert_eq!(moved, expected);
+        assert_eq!((&mut query).into_iter().count(), 65);
+    }
+
+    macro_rules! wide_components {
+        ($($component:ident $uuid:literal),+) => {
+            $(
+                #[derive(Debug, TypeUuid)]
+                #[uuid = $uuid]
+                pub struct $component(pub u32);
+            )+
+        };
+    }
+
+    wide_components!(
+        C1 "6a1b2c3d-0000-4000-8000-000000000001",
+        C2 "6a1b2c3d-0000-4000-8000-000000000002",
+        C3 "6a1b2c3d-0000-4000-8000-000000000003",
+        C4 "6a1b2c3d-0000-4000-8000-000000000004",
+        C5 "6a1b2c3d-0000-4000-8000-000000000005",
+        C6 "6a1b2c3d-0000-4000-8000-000000000006",
+        C7 "6a1b2c3d-0000-4000-8000-000000000007",
+        C8 "6a1b2c3d-0000-4000-8000-000000000008",
+        C9 "6a1b2c3d-0000-4000-8000-000000000009",
+        C10 "6a1b2c3d-0000-4000-8000-000000000010",
+        C11 "6a1b2c3d-0000-4000-8000-000000000011",
+        C12 "6a1b2c3d-0000-4000-8000-000000000012",
+        C13 "6a1b2c3d-0000-4000-8000-000000000013",
+        C14 "6a1b2c3d-0000-4000-8000-000000000014",
+        C15 "6a1b2c3d-0000-4000-8000-000000000015",
+        C16 "6a1b2c3d-0000-4000-8000-000000000016"
+    );
+
+    #[tokio::test]
+    async fn wide_tuples() {
+        let mut world = World::default();
+
+        for i in 0..3 {
+            world::add_entity(&mut world, (
+                C1(i), C2(i), C3(i), C4(i), C5(i), C6(i), C7(i), C8(i),
+                C9(i), C10(i), C11(i), C12(i), C13(i), C14(i), C15(i), C16(i),
+            )).await;
+        }
+
+        {
+            let mut query = world::query::<(
+                &mut C1, &C2, &C3, &C4, &C5, &C6, &C7, &C8,
+                &C9, &C10, &C11, &C12, &C13, &C14, &C15, &C16,
+            ), ()>(&world).await.unwrap();
+
+            for (c1, c2, c3, c4, c5, c6, c7, c8, c9, c10, c11, c12, c13, c14, c15, c16) in &mut query {
+                c1.0 += c2.0 + c3.0 + c4.0 + c5.0 + c6.0 + c7.0 + c8.0
+                    + c9.0 + c10.0 + c11.0 + c12.0 + c13.0 + c14.0 + c15.0 + c16.0;
+            }
+        }
+
+        let mut query = world::query::<(&C1,), ()>(&world).await.unwrap();
+
+        let mut values = (&mut query).into_iter()
+            .map(|(c1,)| c1.0)
+            .collect::<Vec<_>>();
+
+        values.sort();
+
+        assert_eq!(values, vec![0, 16, 32]);
+    }
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "0f4e5d6c-7b8a-4c9d-8e1f-2a3b4c5d6e04"]
+    pub struct Unregistered;
+
+    #[tokio::test]
+    async fn optional_access() {
+        let mut world = World::default();
+
+        for x in 0..40 {
+            world::add_entity(&mut world, (Position { x }, Speed { x: 1 })).await;
+        }
+
+        for x in 0..40 {
+            world::add_entity(&mut world, (Position { x },)).await;
+        }
+
+        {
+            let mut query = world::query::<(&mut Position, Option<&Speed>, Option<&Unregistered>), ()>(&world).await.unwrap();
+
+            let mut count = 0;
+
+            for (position, speed, unregistered) in &mut query {
+                assert!(unregistered.is_none());
+
+                if let Some(speed) = speed {
+                    position.x += speed.x * 100;
+                }
+
+                count += 1;
+            }
+
+            assert_eq!(count, 80);
+        }
+
+        assert!(world::query::<(&Position, &Unregistered), ()>(&world).await.is_none());
+
+        let mut query = world::query::<(Option<&mut Speed>,), ()>(&world).await.unwrap();
+
+        assert_eq!((&mut query).into_iter().filter(|(speed,)| speed.is_some()).count(), 40);
+        assert_eq!((&mut query).into_iter().count(), 80);
+        drop(query);
+
+        let mut query = world::query::<(&Position,), ()>(&world).await.unwrap();
+
+        assert_eq!((&mut query).into_iter().filter(|(position,)| position.x >= 100).count(), 40);
+    }
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "0f4e5d6c-7b8a-4c9d-8e1f-2a3b4c5d6e05"]
+    pub struct Dead;
+
+    #[tokio::test]
+    async fn query_filters() {
+        let mut world = World::default();
+
+        world::add_entity(&mut world, (Position { x: 1 }, Player)).await;
+        world::add_entity(&mut world, (Position { x: 2 }, Player, Dead)).await;
+        world::add_entity(&mut world, (Position { x: 3 }, Dead)).await;
+        world::add_entity(&mut world, (Position { x: 4 }, Speed { x: 0 })).await;
+        world::add_entity(&mut world, (Position { x: 5 },)).await;
+
+        async fn positions<TFilter: IQueryFilter>(world: &World) -> Vec<u32> {
+            let mut query = world::query::<(&Position,), TFilter>(world).await.unwrap();
+
+            let mut positions = (&mut query).into_iter()
+                .map(|(position,)| position.x)
+                .collect::<Vec<_>>();
+
+            positions.sort();
+            positions
+        }
+
+        assert_eq!(positions::<()>(&world).await, vec![1, 2, 3, 4, 5]);
+        assert_eq!(positions::<With<Player>>(&world).await, vec![1, 2]);
+        assert_eq!(positions::<(With<Player>, Without<Dead>)>(&world).await, vec![1]);
+        assert_eq!(positions::<Or<(With<Player>, With<Speed>)>>(&world).await, vec![1, 2, 4]);
+        assert_eq!(positions::<Not<Or<(With<Player>, With<Dead>)>>>(&world).await, vec![4, 5]);
+        assert_eq!(positions::<(Without<Player>, Not<With<Dead>>, Without<Speed>)>(&world).await, vec![5]);
+    }
+
+    #[tokio::test]
+    async fn change_detection() {
+        let mut world = World::default();
+
+        for x in 0..10 {
+            world::add_entity(&mut world, (Position { x },)).await;
+            world::add_entity(&mut world, (Position { x }, Player)).await;
+        }
+
+        async fn changed(world: &World, last_change_tick: u64) -> (usize, u64) {
+            let mut query = world::query_since::<(&Position,), Changed<Position>>(world, last_change_tick).await.unwrap();
+            ((&mut query).into_iter().count(), world::query_change_tick(&query))
+        }
+
+        let (count, last_change_tick) = changed(&world, 0).await;
+        assert_eq!(count, 20);
+
+        let (count, last_change_tick) = changed(&world, last_change_tick).await;
+        assert_eq!(count, 0);
+
+        {
+            let mut query = world::query::<(&mut Position,), With<Player>>(&world).await.unwrap();
+
+            for (position,) in &mut query {
+                position.x += 1;
+            }
+        }
+
+        let (count, last_change_tick) = changed(&world, last_change_tick).await;
+        assert_eq!(count, 10);
+
+        world::add_entity(&mut world, (Position { x: 100 },)).await;
+
+        {
+            let mut query = world::query_since::<(&Position,), Added<Position>>(&world, last_change_tick).await.unwrap();
+            // тики ведутся на чанк, поэтому видны все строки чанка, в который добавили сущность
+            assert_eq!((&mut query).into_iter().count(), 11);
+        }
+
+        {
+            let mut query = world::query_since::<(&Position,), (Changed<Position>, Without<Player>)>(&world, last_change_tick).await.unwrap();
+            assert_eq!((&mut query).into_iter().count(), 11);
+        }
+
+        {
+            let mut query = world::query_since::<(&Position,), Not<Changed<Position>>>(&world, last_change_tick).await.unwrap();
+            assert_eq!((&mut query).into_iter().count(), 10);
+        }
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "not in the access tuple")]
+    async fn tick_filter_outside_access() {
+        let world = World::default();
+
+        // без Player в доступе тики его чанков недоступны, и выборка отвергается, а не выглядит пустой
+        let _ = world::query::<(&Position,), Changed<Player>>(&world).await;
+    }
+
+    #[tokio::test]
+    async fn ticks_survive_migration() {
+        let mut world = World::default();
+
+        let entity_id = world::add_entity(&mut world, (Position { x: 1 }, Speed { x: 1 })).await;
+
+        async fn matched<TFilter: IQueryFilter>(world: &World, last_change_tick: u64) -> usize {
+            let mut query = world::query_since::<(&Position, Option<&Player>), TFilter>(world, last_change_tick).await.unwrap();
+            (&mut query).into_iter().count()
+        }
+
+        let last_change_tick = {
+            let query = world::query_since::<(&Position,), Added<Position>>(&world, 0).await.unwrap();
+            world::query_change_tick(&query)
+        };
+
+        // переезд в другой архетип из-за постороннего компонента не делает Position добавленным или измененным
+        assert!(world::insert_component(&mut world, entity_id, Player).await);
+
+        assert_eq!(matched::<Added<Position>>(&world, last_change_tick).await, 0);
+        assert_eq!(matched::<Changed<Position>>(&world, last_change_tick).await, 0);
+        assert_eq!(matched::<Added<Player>>(&world, last_change_tick).await, 1);
+
+        assert!(world::remove_component::<Speed>(&mut world, entity_id).await.is_some());
+
+        assert_eq!(matched::<Added<Position>>(&world, last_change_tick).await, 0);
+        assert_eq!(matched::<Changed<Position>>(&world, last_change_tick).await, 0);
+        assert_eq!(matched::<Added<Player>>(&world, last_change_tick).await, 1);
+    }
+
+    #[tokio::test]
+    async fn query_state() {
+        let mut world = World::default();
+
+        world::add_entity(&mut world, (Player,)).await;
+
+        let mut state = world::query_state::<(&Position,), Without<Dead>>();
+
+        // Position еще нет в мире, архетипы с ним появятся только после регистрации компонента
+        world::update_query_state(&mut state, &world);
+        assert!(world::query_state_archetypes(&state).is_empty());
+
+        world::add_entity(&mut world, (Position { x: 1 },)).await;
+        world::add_entity(&mut world, (Position { x: 2 }, Dead)).await;
+
+        async fn positions(state: &mut world::QueryState<(&'static Position,), Without<Dead>>, world: &World) -> Vec<u32> {
+            let mut query = world::query_with_state(state, world, 0).await.unwrap();
+
+            let mut positions = (&mut query).into_iter()
+                .map(|(position,)| position.x)
+                .collect::<Vec<_>>();
+
+            positions.sort();
+            positions
+        }
+
+        assert_eq!(positions(&mut state, &world).await, vec![1]);
+        assert_eq!(world::query_state_archetypes(&state).len(), 1);
+
+        // форма мира не менялась: новые сущности в старых архетипах видны без повторного сопоставления
+        world::add_entity(&mut world, (Position { x: 3 },)).await;
+        assert_eq!(positions(&mut state, &world).await, vec![1, 3]);
+        assert_eq!(world::query_state_archetypes(&state).len(), 1);
+
+        let generation = world::archetype_generation(&world);
+
+        world::add_entity(&mut world, (Position { x: 4 }, Player)).await;
+        world::add_entity(&mut world, (Position { x: 5 }, Player, Dead)).await;
+
+        assert_eq!(world::archetype_generation(&world), generation + 2);
+        assert_eq!(positions(&mut state, &world).await, vec![1, 3, 4]);
+        assert_eq!(world::query_state_archetypes(&state).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn component_index() {
+        let mut world = World::default();
+
+        world::add_entity(&mut world, (Position { x: 1 },)).await;
+        world::add_entity(&mut world, (Position { x: 2 }, Speed { x: 0 })).await;
+        world::add_entity(&mut world, (Position { x: 3 }, Dead)).await;
+        world::add_entity(&mut world, (Position { x: 4 }, Speed { x: 0 }, Dead)).await;
+        let player = world::add_entity(&mut world, (Position { x: 5 }, Player)).await;
+        world::add_entity(&mut world, (Speed { x: 6 }, Player)).await;
+
+        let archetypes = |uuid: [u8; 16]| world::component_archetypes(&world, world::component_id(&world, Uuid::from_bytes(uuid)).unwrap()).to_vec();
+
+        let positions = archetypes(Position::UUID);
+        let players = archetypes(Player::UUID);
+
+        assert_eq!(positions.len(), 5);
+        assert_eq!(players.len(), 2);
+        assert_eq!(archetypes(EntityId::UUID).len(), 6);
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(players.contains(&world::location(&world, player).unwrap().archetype));
+
+        let mut query = world::query::<(&EntityId, &Position, &Player), ()>(&world).await.unwrap();
+        assert_eq!((&mut query).into_iter().map(|(entity_id, ..)| *entity_id).collect::<Vec<_>>(), vec![player]);
+        drop(query);
+
+        let mut query = world::query::<(&Speed, Option<&Player>), Without<Dead>>(&world).await.unwrap();
+        assert_eq!((&mut query).into_iter().count(), 2);
+        drop(query);
+
+        let mut state = world::query_state::<(&Position, &Dead), ()>();
+        world::update_query_state(&mut state, &world);
+
+        let mut expected = archetypes(Dead::UUID);
+        expected.sort();
+
+        let mut matched = world::query_state_archetypes(&state).to_vec();
+        matched.sort();
+
+        assert_eq!(matched, expected);
+    }
+}
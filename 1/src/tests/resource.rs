@@ -0,0 +1,122 @@
+#[cfg(test)]
+pub mod resource {
+    use type_uuid::TypeUuid;
+    use uuid::Uuid;
+
+    use crate::{world::{self, World, Res, ResMut, EventReader, EventWriter}, system::ComponentAccess};
+
+    #[derive(Debug, TypeUuid, PartialEq)]
+    #[uuid = "e1d2c3b4-a596-4788-99aa-bbccddeeff01"]
+    pub struct DeltaTime(pub u32);
+
+    #[derive(Debug, TypeUuid, PartialEq)]
+    #[uuid = "e1d2c3b4-a596-4788-99aa-bbccddeeff02"]
+    pub struct FrameCounter(pub u32);
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "e1d2c3b4-a596-4788-99aa-bbccddeeff03"]
+    pub struct Position(pub u32);
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "e1d2c3b4-a596-4788-99aa-bbccddeeff04"]
+    pub struct Hit;
+
+    #[tokio::test]
+    async fn insert_and_access() {
+        let mut world = World::default();
+
+        assert!(world::resource::<DeltaTime>(&world).await.is_none());
+        assert!(!world::contains_resource::<DeltaTime>(&world));
+
+        assert_eq!(world::insert_resource(&mut world, DeltaTime(16)).await, None);
+        assert_eq!(world::insert_resource(&mut world, DeltaTime(33)).await, Some(DeltaTime(16)));
+
+        assert!(world::contains_resource::<DeltaTime>(&world));
+        assert_eq!(*world::resource::<DeltaTime>(&world).await.unwrap(), DeltaTime(33));
+
+        world::resource_mut::<DeltaTime>(&world).await.unwrap().0 = 8;
+
+        assert_eq!(*world::resource::<DeltaTime>(&world).await.unwrap(), DeltaTime(8));
+    }
+
+    #[tokio::test]
+    async fn resources_in_access_tuples() {
+        let mut world = World::default();
+
+        for x in 0..3 {
+            world::add_entity(&mut world, (Position(x),)).await;
+        }
+
+        // без ресурса выборка не собирается, а опциональный доступ дает None
+        assert!(world::query::<(&mut Position, Res<DeltaTime>), ()>(&world).await.is_none());
+
+        {
+            let mut query = world::query::<(&Position, Option<Res<DeltaTime>>), ()>(&world).await.unwrap();
+            assert!((&mut query).into_iter().all(|(_, delta_time)| delta_time.is_none()));
+        }
+
+        world::insert_resource(&mut world, DeltaTime(10)).await;
+        world::insert_resource(&mut world, FrameCounter(0)).await;
+
+        {
+            let mut query = world::query::<(&mut Position, Res<DeltaTime>, ResMut<FrameCounter>), ()>(&world).await.unwrap();
+
+            for (position, delta_time, ()) in &mut query {
+                position.0 += delta_time.0;
+            }
+        }
+
+        {
+            let (_delta_time, mut frame_counter) = world::get::<(Res<DeltaTime>, ResMut<FrameCounter>)>(&world).await.unwrap();
+            frame_counter.0 += 1;
+        }
+
+        let mut query = world::query::<(&Position,), ()>(&world).await.unwrap();
+        let mut positions = (&mut query).into_iter().map(|(position,)| position.0).collect::<Vec<_>>();
+        positions.sort();
+
+        assert_eq!(positions, vec![10, 11, 12]);
+        assert_eq!(*world::resource::<FrameCounter>(&world).await.unwrap(), FrameCounter(1));
+    }
+
+    #[tokio::test]
+    async fn optional_resources_in_queries() {
+        let mut world = World::default();
+
+        for x in 0..2 {
+            world::add_entity(&mut world, (Position(x),)).await;
+        }
+
+        world::insert_resource(&mut world, DeltaTime(5)).await;
+        world::insert_resource(&mut world, FrameCounter(0)).await;
+        world::register_event::<Hit>(&mut world);
+
+        // у ресурса нет чанков в архетипе, но для каждой строки он есть
+        {
+            let mut query = world::query::<(&Position, Option<Res<DeltaTime>>, Option<ResMut<FrameCounter>>, Option<EventReader<Hit>>), ()>(&world).await.unwrap();
+
+            let rows = (&mut query).into_iter()
+                .map(|(_, delta_time, frame_counter, hits)| (delta_time.map(|x| x.0), frame_counter.is_some(), hits.is_some()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(rows, vec![(Some(5), true, true); 2]);
+        }
+
+        let mut query = world::query::<(&Position, Option<EventWriter<Hit>>), ()>(&world).await.unwrap();
+        assert!((&mut query).into_iter().all(|(_, hits)| hits.is_some()));
+    }
+
+    #[test]
+    fn resource_access_sets() {
+        use crate::world::IAccessManager;
+
+        assert_eq!(<(&mut Position, Res<DeltaTime>, ResMut<FrameCounter>)>::component_access(), ComponentAccess {
+            reads: [Uuid::from_bytes(DeltaTime::UUID)].into(),
+            writes: [Uuid::from_bytes(Position::UUID), Uuid::from_bytes(FrameCounter::UUID)].into(),
+            required: [Uuid::from_bytes(Position::UUID)].into(),
+            resources: [Uuid::from_bytes(DeltaTime::UUID), Uuid::from_bytes(FrameCounter::UUID)].into(),
+        });
+
+        assert_eq!(<(&Position, Res<DeltaTime>)>::required_uuids(), vec![Uuid::from_bytes(Position::UUID)]);
+    }
+}
@@ -0,0 +1,477 @@
+#[cfg(test)]
+pub mod scheduler {
+    use std::{any::type_name, sync::{Arc, Mutex, atomic::{AtomicBool, AtomicUsize, Ordering}}, time::{Duration, Instant}, thread::ThreadId};
+
+    use tokio::sync::{Barrier, Notify};
+    use type_uuid::TypeUuid;
+    use uuid::Uuid;
+
+    use crate::{world::{World, self}, system::{self, ISystem, ComponentAccess}, scheduler::{self, ScheduleError}, commands::Commands, sync::RwLock};
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    macro_rules! log_system {
+        ($system:ident, $uuid:literal, $name:literal) => {
+            #[derive(TypeUuid)]
+            #[uuid = $uuid]
+            pub struct $system {
+                log: Log,
+            }
+
+            impl ISystem for $system {
+                type TQuery = ();
+                type TProps<'frame> = ();
+
+                async fn query<'frame>(&mut self, _world: &'frame World) -> Option<Self::TProps<'frame>> {
+                    Some(())
+                }
+
+                async fn system<'frame>(&mut self, _props: Self::TProps<'frame>, _world: &'frame World, _commands: &mut Commands) {
+                    tokio::task::yield_now().await;
+                    self.log.lock().unwrap().push($name);
+                }
+            }
+        };
+    }
+
+    log_system!(InputSystem, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e901", "input");
+    log_system!(PhysicsSystem, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e902", "physics");
+    log_system!(RenderSystem, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e903", "render");
+
+    #[tokio::test]
+    async fn ordering() {
+        let world = Arc::new(RwLock::new(World::default()));
+        let log = Log::default();
+
+        let mut scheduler = scheduler::new();
+
+        scheduler::add_system(&mut scheduler, RenderSystem { log: log.clone() }).unwrap();
+        scheduler::add_system(&mut scheduler, PhysicsSystem { log: log.clone() }).unwrap();
+        scheduler::add_system(&mut scheduler, InputSystem { log: log.clone() }).unwrap();
+
+        scheduler::before::<InputSystem, PhysicsSystem>(&mut scheduler);
+        scheduler::after::<RenderSystem, PhysicsSystem>(&mut scheduler);
+
+        assert!(matches!(
+            scheduler::add_system(&mut scheduler, InputSystem { log: log.clone() }),
+            Err(ScheduleError::DuplicateSystem { .. })
+        ));
+
+        for _ in 0..3 {
+            scheduler::run(&mut scheduler, &world).await.unwrap();
+        }
+
+        assert_eq!(*log.lock().unwrap(), ["input", "physics", "render"].repeat(3));
+    }
+
+    #[tokio::test]
+    async fn cycle() {
+        let log = Log::default();
+
+        let mut scheduler = scheduler::new();
+
+        scheduler::add_system(&mut scheduler, InputSystem { log: log.clone() }).unwrap();
+        scheduler::add_system(&mut scheduler, PhysicsSystem { log: log.clone() }).unwrap();
+        scheduler::add_system(&mut scheduler, RenderSystem { log: log.clone() }).unwrap();
+
+        scheduler::before::<InputSystem, PhysicsSystem>(&mut scheduler);
+        scheduler::before::<PhysicsSystem, RenderSystem>(&mut scheduler);
+        scheduler::before::<RenderSystem, InputSystem>(&mut scheduler);
+
+        let Err(ScheduleError::Cycle { systems }) = scheduler::build(&mut scheduler) else {
+            panic!("cycle was not detected");
+        };
+
+        assert_eq!(systems.len(), 4);
+        assert_eq!(systems.first().unwrap().name, systems.last().unwrap().name);
+    }
+
+    #[derive(TypeUuid)]
+    #[uuid = "9a0c1d2e-3f40-4152-8364-a5b6c7d8e904"]
+    pub struct BarrierSystemA(Arc<Barrier>);
+
+    #[derive(TypeUuid)]
+    #[uuid = "9a0c1d2e-3f40-4152-8364-a5b6c7d8e905"]
+    pub struct BarrierSystemB(Arc<Barrier>);
+
+    impl ISystem for BarrierSystemA {
+        type TQuery = ();
+        type TProps<'frame> = ();
+
+        async fn query<'frame>(&mut self, _world: &'frame World) -> Option<Self::TProps<'frame>> {
+            Some(())
+        }
+
+        async fn system<'frame>(&mut self, _props: Self::TProps<'frame>, _world: &'frame World, _commands: &mut Commands) {
+            self.0.wait().await;
+        }
+    }
+
+    impl ISystem for BarrierSystemB {
+        type TQuery = ();
+        type TProps<'frame> = ();
+
+        async fn query<'frame>(&mut self, _world: &'frame World) -> Option<Self::TProps<'frame>> {
+            Some(())
+        }
+
+        async fn system<'frame>(&mut self, _props: Self::TProps<'frame>, _world: &'frame World, _commands: &mut Commands) {
+            self.0.wait().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn independent_systems_run_concurrently() {
+        let world = Arc::new(RwLock::new(World::default()));
+        let barrier = Arc::new(Barrier::new(2));
+
+        let mut scheduler = scheduler::new();
+
+        scheduler::add_system(&mut scheduler, BarrierSystemA(barrier.clone())).unwrap();
+        scheduler::add_system(&mut scheduler, BarrierSystemB(barrier.clone())).unwrap();
+
+        // при последовательном запуске первая система навсегда застрянет на барьере
+        tokio::time::timeout(Duration::from_secs(5), scheduler::run(&mut scheduler, &world)).await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "9a0c1d2e-3f40-4152-8364-a5b6c7d8e910"]
+    pub struct Position(pub u32);
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "9a0c1d2e-3f40-4152-8364-a5b6c7d8e911"]
+    pub struct Speed(pub u32);
+
+    macro_rules! access_system {
+        ($system:ident, $uuid:literal, $query:ty) => {
+            #[derive(TypeUuid)]
+            #[uuid = $uuid]
+            pub struct $system;
+
+            impl ISystem for $system {
+                type TQuery = $query;
+                type TProps<'frame> = ();
+
+                async fn query<'frame>(&mut self, _world: &'frame World) -> Option<Self::TProps<'frame>> {
+                    Some(())
+                }
+
+                async fn system<'frame>(&mut self, _props: Self::TProps<'frame>, _world: &'frame World, _commands: &mut Commands) {}
+            }
+        };
+    }
+
+    access_system!(MoveSystem, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e906", (&'static mut Position, &'static Speed));
+    access_system!(TeleportSystem, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e907", (&'static mut Position,));
+    access_system!(SpeedReadSystem, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e908", (&'static Speed, Option<&'static Position>));
+    access_system!(AccelerateSystem, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e909", (&'static mut Speed,));
+
+    #[tokio::test]
+    async fn access_conflicts() {
+        let world = Arc::new(RwLock::new(World::default()));
+
+        assert_eq!(system::access::<MoveSystem>(), ComponentAccess {
+            reads: [Uuid::from_bytes(Speed::UUID)].into(),
+            writes: [Uuid::from_bytes(Position::UUID)].into(),
+            required: [Uuid::from_bytes(Position::UUID), Uuid::from_bytes(Speed::UUID)].into(),
+            resources: [].into(),
+        });
+
+        let mut scheduler = scheduler::new();
+
+        scheduler::add_system(&mut scheduler, MoveSystem).unwrap();
+        scheduler::add_system(&mut scheduler, TeleportSystem).unwrap();
+        scheduler::add_system(&mut scheduler, SpeedReadSystem).unwrap();
+        scheduler::add_system(&mut scheduler, AccelerateSystem).unwrap();
+
+        // ускорение явно идет до движения, этот конфликт уже разрешен порядком
+        scheduler::before::<AccelerateSystem, MoveSystem>(&mut scheduler);
+
+        scheduler::build(&mut scheduler).unwrap();
+
+        let conflicts = scheduler::conflicts(&scheduler).iter()
+            .map(|conflict| (conflict.first.name, conflict.second.name, conflict.components.clone()))
+            .collect::<Vec<_>>();
+
+        let position = vec![Uuid::from_bytes(Position::UUID)];
+
+        // чтение скорости упорядочено с ускорением транзитивно: ускорение -> движение -> чтение
+        assert_eq!(conflicts, vec![
+            (type_name::<MoveSystem>(), type_name::<TeleportSystem>(), position.clone()),
+            (type_name::<MoveSystem>(), type_name::<SpeedReadSystem>(), position.clone()),
+            (type_name::<TeleportSystem>(), type_name::<SpeedReadSystem>(), position),
+        ]);
+
+        scheduler::run(&mut scheduler, &world).await.unwrap();
+    }
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "9a0c1d2e-3f40-4152-8364-a5b6c7d8e912"]
+    pub struct Player;
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "9a0c1d2e-3f40-4152-8364-a5b6c7d8e913"]
+    pub struct Bullet;
+
+    macro_rules! barrier_move_system {
+        ($system:ident, $uuid:literal, $marker:ty) => {
+            #[derive(TypeUuid)]
+            #[uuid = $uuid]
+            pub struct $system(Arc<Barrier>);
+
+            impl ISystem for $system {
+                type TQuery = (&'static mut Position, &'static $marker);
+                type TProps<'frame> = world::Query<'frame, Self::TQuery>;
+
+                async fn query<'frame>(&mut self, world: &'frame World) -> Option<Self::TProps<'frame>> {
+                    world::query::<Self::TQuery, ()>(world).await
+                }
+
+                // колонки Position держатся, пока обе системы не дойдут до барьера
+                async fn system<'frame>(&mut self, mut props: Self::TProps<'frame>, _world: &'frame World, _commands: &mut Commands) {
+                    for (position, _) in &mut props {
+                        position.0 += 1;
+                    }
+
+                    self.0.wait().await;
+                }
+            }
+        };
+    }
+
+    barrier_move_system!(MovePlayersSystem, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e914", Player);
+    barrier_move_system!(MoveBulletsSystem, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e915", Bullet);
+
+    #[tokio::test]
+    async fn disjoint_archetypes_run_concurrently() {
+        let world = Arc::new(RwLock::new(World::default()));
+
+        {
+            let mut world = world.write().await;
+
+            world::add_entity(&mut world, (Position(0), Player)).await;
+            world::add_entity(&mut world, (Position(10), Bullet)).await;
+        }
+
+        let barrier = Arc::new(Barrier::new(2));
+
+        let mut scheduler = scheduler::new();
+
+        scheduler::add_system(&mut scheduler, MovePlayersSystem(barrier.clone())).unwrap();
+        scheduler::add_system(&mut scheduler, MoveBulletsSystem(barrier.clone())).unwrap();
+
+        // по одним типам обе системы пишут Position
+        scheduler::build(&mut scheduler).unwrap();
+        assert_eq!(scheduler::conflicts(&scheduler).len(), 1);
+
+        // при общей блокировке колонки Position вторая система ждала бы первую, застрявшую на барьере
+        tokio::time::timeout(Duration::from_secs(5), scheduler::run(&mut scheduler, &world)).await
+            .unwrap()
+            .unwrap();
+
+        assert!(scheduler::conflicts(&scheduler).is_empty());
+
+        // сущность с обоими маркерами попадает в обе выборки, и системы снова идут по очереди
+        world::add_entity(&mut *world.write().await, (Position(20), Player, Bullet)).await;
+
+        scheduler::build_for_world(&mut scheduler, &*world.read().await).unwrap();
+        assert_eq!(scheduler::conflicts(&scheduler).len(), 1);
+
+        let world = world.read().await;
+        let mut query = world::query::<(&Position,), ()>(&world).await.unwrap();
+        let mut positions = (&mut query).into_iter().map(|(position,)| position.0).collect::<Vec<_>>();
+        positions.sort();
+
+        assert_eq!(positions, vec![1, 11, 20]);
+    }
+
+    #[derive(Default)]
+    pub struct Overlap {
+        started: AtomicUsize,
+        threads: Mutex<Vec<(ThreadId, bool)>>,
+    }
+
+    macro_rules! spin_system {
+        ($system:ident, $uuid:literal) => {
+            #[derive(TypeUuid)]
+            #[uuid = $uuid]
+            pub struct $system(Arc<Overlap>);
+
+            impl ISystem for $system {
+                type TQuery = ();
+                type TProps<'frame> = ();
+
+                async fn query<'frame>(&mut self, _world: &'frame World) -> Option<Self::TProps<'frame>> {
+                    Some(())
+                }
+
+                // система не отдает управление: вторая может начаться только на другом потоке
+                async fn system<'frame>(&mut self, _props: Self::TProps<'frame>, _world: &'frame World, _commands: &mut Commands) {
+                    self.0.started.fetch_add(1, Ordering::SeqCst);
+
+                    let deadline = Instant::now() + Duration::from_secs(2);
+
+                    while self.0.started.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+                        std::thread::yield_now();
+                    }
+
+                    let overlapped = self.0.started.load(Ordering::SeqCst) == 2;
+                    self.0.threads.lock().unwrap().push((std::thread::current().id(), overlapped));
+                }
+            }
+        };
+    }
+
+    spin_system!(SpinSystemA, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e916");
+    spin_system!(SpinSystemB, "9a0c1d2e-3f40-4152-8364-a5b6c7d8e917");
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn independent_systems_run_in_parallel() {
+        let world = Arc::new(RwLock::new(World::default()));
+        let overlap = Arc::new(Overlap::default());
+
+        let mut scheduler = scheduler::new();
+
+        scheduler::add_system(&mut scheduler, SpinSystemA(overlap.clone())).unwrap();
+        scheduler::add_system(&mut scheduler, SpinSystemB(overlap.clone())).unwrap();
+
+        // кадр целиком можно отдать в отдельную задачу
+        let (_, result) = tokio::spawn(async move {
+            let result = scheduler::run(&mut scheduler, &world).await;
+            (scheduler, result)
+        }).await.unwrap();
+
+        result.unwrap();
+
+        let threads = overlap.threads.lock().unwrap();
+
+        assert_eq!(threads.len(), 2);
+        assert!(threads.iter().all(|(_, overlapped)| *overlapped));
+        assert_ne!(threads[0].0, threads[1].0);
+    }
+
+    // первый кадр система падает или зависает, дальше просто считает запуски
+    #[derive(TypeUuid)]
+    #[uuid = "9a0c1d2e-3f40-4152-8364-a5b6c7d8e918"]
+    pub struct FlakySystem {
+        runs: Arc<AtomicUsize>,
+        panic_once: AtomicBool,
+        stall_once: Option<Arc<Notify>>,
+    }
+
+    impl ISystem for FlakySystem {
+        type TQuery = ();
+        type TProps<'frame> = ();
+
+        async fn query<'frame>(&mut self, _world: &'frame World) -> Option<Self::TProps<'frame>> {
+            Some(())
+        }
+
+        async fn system<'frame>(&mut self, _props: Self::TProps<'frame>, _world: &'frame World, _commands: &mut Commands) {
+            if let Some(release) = self.stall_once.take() {
+                release.notified().await;
+            }
+
+            assert!(!self.panic_once.swap(false, Ordering::SeqCst), "flaky system");
+
+            self.runs.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn flaky_scheduler(runs: &Arc<AtomicUsize>, log: &Log, panic_once: bool, stall_once: Option<Arc<Notify>>) -> scheduler::Scheduler {
+        let mut scheduler = scheduler::new();
+
+        scheduler::add_system(&mut scheduler, FlakySystem {
+            runs: runs.clone(),
+            panic_once: AtomicBool::new(panic_once),
+            stall_once,
+        }).unwrap();
+        scheduler::add_system(&mut scheduler, RenderSystem { log: log.clone() }).unwrap();
+
+        scheduler::before::<FlakySystem, RenderSystem>(&mut scheduler);
+
+        scheduler
+    }
+
+    #[tokio::test]
+    async fn panicking_system() {
+        let world = Arc::new(RwLock::new(World::default()));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let log = Log::default();
+
+        let mut scheduler = flaky_scheduler(&runs, &log, true, None);
+
+        // зависящая система в кадре с паникой не стартует
+        assert!(matches!(
+            scheduler::run(&mut scheduler, &world).await,
+            Err(ScheduleError::SystemPanicked { system }) if system.name == type_name::<FlakySystem>()
+        ));
+        assert!(log.lock().unwrap().is_empty());
+
+        scheduler::run(&mut scheduler, &world).await.unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(*log.lock().unwrap(), vec!["render"]);
+    }
+
+    #[tokio::test]
+    async fn dropped_run() {
+        let world = Arc::new(RwLock::new(World::default()));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let log = Log::default();
+
+        let release = Arc::new(Notify::new());
+
+        let mut scheduler = flaky_scheduler(&runs, &log, false, Some(release.clone()));
+
+        assert!(tokio::time::timeout(Duration::from_millis(10), scheduler::run(&mut scheduler, &world)).await.is_err());
+
+        release.notify_one();
+
+        // брошенный кадр дорабатывает в своей задаче, следующий кадр его дожидается
+        scheduler::run(&mut scheduler, &world).await.unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert_eq!(*log.lock().unwrap(), vec!["render"]);
+    }
+
+    #[derive(TypeUuid)]
+    #[uuid = "9a0c1d2e-3f40-4152-8364-a5b6c7d8e919"]
+    pub struct ThreadSystem(Arc<Mutex<Vec<ThreadId>>>);
+
+    impl ISystem for ThreadSystem {
+        type TQuery = ();
+        type TProps<'frame> = ();
+
+        async fn query<'frame>(&mut self, _world: &'frame World) -> Option<Self::TProps<'frame>> {
+            Some(())
+        }
+
+        async fn system<'frame>(&mut self, _props: Self::TProps<'frame>, _world: &'frame World, _commands: &mut Commands) {
+            self.0.lock().unwrap().push(std::thread::current().id());
+        }
+    }
+
+    #[tokio::test]
+    async fn frames_reuse_threads() {
+        let world = Arc::new(RwLock::new(World::default()));
+        let threads = Arc::new(Mutex::new(vec![]));
+
+        let mut scheduler = scheduler::new();
+        scheduler::add_system(&mut scheduler, ThreadSystem(threads.clone())).unwrap();
+
+        for _ in 0..32 {
+            scheduler::run(&mut scheduler, &world).await.unwrap();
+        }
+
+        // задачи кадров идут на рабочие потоки исполнителя, а не на новый поток для каждой системы
+        let mut threads = threads.lock().unwrap().clone();
+        threads.sort_by_key(|thread| format!("{thread:?}"));
+        threads.dedup();
+
+        let pool_size = std::thread::available_parallelism().map_or(2, |threads| threads.get().max(2));
+        assert!(threads.len() <= pool_size);
+    }
+}
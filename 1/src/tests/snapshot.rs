@@ -0,0 +1,158 @@
+#[cfg(test)]
+pub mod snapshot {
+    use serde::{Serialize, Deserialize};
+    use type_uuid::TypeUuid;
+    use uuid::Uuid;
+
+    use crate::{archetype, world::{self, World}, entity::{EntityId, self}, snapshot::{SnapshotError, self}};
+
+    #[derive(Debug, TypeUuid, Clone, PartialEq, Serialize, Deserialize)]
+    #[uuid = "0d9e8f7a-6b5c-4d3e-8f21-a0b1c2d3e401"]
+    pub struct Position {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    #[derive(Debug, TypeUuid, Clone, PartialEq, Serialize, Deserialize)]
+    #[uuid = "0d9e8f7a-6b5c-4d3e-8f21-a0b1c2d3e402"]
+    pub struct Name(pub String);
+
+    #[derive(Debug, TypeUuid)]
+    #[uuid = "0d9e8f7a-6b5c-4d3e-8f21-a0b1c2d3e403"]
+    pub struct Transient;
+
+    // ссылка на другую сущность внутри данных компонента
+    #[derive(Debug, TypeUuid, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    #[uuid = "0d9e8f7a-6b5c-4d3e-8f21-a0b1c2d3e404"]
+    pub struct Target(pub EntityId);
+
+    async fn populate(world: &mut World) {
+        // больше одного чанка в архетипе
+        for x in 0..40 {
+            world::add_entity(world, (Position { x, y: -x },)).await;
+        }
+
+        for x in 0..3 {
+            world::add_entity(world, (Position { x, y: x }, Name(format!("entity {x}")))).await;
+        }
+
+        world::add_entity(world, ()).await;
+    }
+
+    async fn rows(world: &World) -> Vec<(EntityId, Option<Position>, Option<Name>)> {
+        let mut query = world::query::<(&EntityId, Option<&Position>, Option<&Name>), ()>(world).await.unwrap();
+
+        let mut rows = (&mut query).into_iter()
+            .map(|(entity_id, position, name)| (*entity_id, position.cloned(), name.cloned()))
+            .collect::<Vec<_>>();
+
+        rows.sort_by_key(|(entity_id, ..)| format!("{entity_id:?}"));
+
+        rows
+    }
+
+    fn registry() -> snapshot::SnapshotRegistry {
+        let mut registry = snapshot::new();
+
+        snapshot::register::<Position>(&mut registry);
+        snapshot::register::<Name>(&mut registry);
+
+        registry
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let mut world = World::default();
+        populate(&mut world).await;
+
+        let registry = registry();
+        let expected = rows(&world).await;
+
+        assert_eq!(expected.len(), 44);
+
+        let json = snapshot::to_json(&registry, &world).await.unwrap();
+        let loaded = snapshot::from_json(&registry, &json).await.unwrap();
+
+        assert_eq!(rows(&loaded).await, expected);
+
+        let bytes = snapshot::to_binary(&registry, &world).await.unwrap();
+        let loaded = snapshot::from_binary(&registry, &bytes).await.unwrap();
+
+        assert_eq!(rows(&loaded).await, expected);
+        assert!(bytes.len() < json.len());
+
+        let (entity_id, ..) = expected[0];
+        // номера архетипов зависят от порядка создания, сравнивается набор компонентов
+        let components = |world: &World| archetype::components(world::archetype(world, world::location(world, entity_id).unwrap().archetype).unwrap()).clone();
+
+        assert_eq!(components(&loaded), components(&world));
+    }
+
+    #[tokio::test]
+    async fn entity_generations() {
+        let mut world = World::default();
+
+        let first = world::add_entity(&mut world, (Position { x: 0, y: 0 },)).await;
+        let second = world::add_entity(&mut world, (Position { x: 1, y: 1 },)).await;
+
+        // слот first переиспользован, слот second свободен, а ссылка на second осталась в данных
+        assert!(world::remove_entity(&mut world, first).await);
+        let hunter = world::add_entity(&mut world, (Target(second),)).await;
+        assert!(world::remove_entity(&mut world, second).await);
+
+        let mut registry = registry();
+        snapshot::register::<Target>(&mut registry);
+
+        let json = snapshot::to_json(&registry, &world).await.unwrap();
+        let bytes = snapshot::to_binary(&registry, &world).await.unwrap();
+
+        let respawned = world::add_entity(&mut world, (Position { x: 2, y: 2 },)).await;
+
+        for mut loaded in [snapshot::from_json(&registry, &json).await.unwrap(), snapshot::from_binary(&registry, &bytes).await.unwrap()] {
+            assert!(world::is_alive(&loaded, hunter));
+            assert!(!world::is_alive(&loaded, first));
+            assert!(!world::is_alive(&loaded, second));
+
+            // новая сущность занимает слот second со следующим поколением, как и в исходном мире
+            let spawned = world::add_entity(&mut loaded, (Position { x: 2, y: 2 },)).await;
+
+            assert_eq!(spawned, respawned);
+            assert_eq!(entity::index(spawned), entity::index(second));
+
+            let target = {
+                let mut query = world::query::<(&Target,), ()>(&loaded).await.unwrap();
+                (&mut query).into_iter().map(|(target,)| target.0).next().unwrap()
+            };
+
+            assert_eq!(target, second);
+            assert!(!world::is_alive(&loaded, target));
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_components() {
+        let mut world = World::default();
+        populate(&mut world).await;
+
+        let json = snapshot::to_json(&registry(), &world).await.unwrap();
+
+        let mut partial = snapshot::new();
+        snapshot::register::<Position>(&mut partial);
+
+        let Err(SnapshotError::UnknownComponents { components }) = snapshot::from_json(&partial, &json).await else {
+            panic!("unknown component was not reported");
+        };
+
+        assert_eq!(components, vec![Uuid::from_bytes(Name::UUID)]);
+
+        world::add_entity(&mut world, (Transient,)).await;
+
+        let Err(SnapshotError::UnregisteredComponents { components }) = snapshot::to_binary(&registry(), &world).await else {
+            panic!("unregistered component was not reported");
+        };
+
+        assert_eq!(components, vec![Uuid::from_bytes(Transient::UUID)]);
+
+        assert!(matches!(snapshot::from_binary(&registry(), &[1, 2, 3]).await, Err(SnapshotError::Binary { .. })));
+    }
+}
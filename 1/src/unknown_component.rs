@@ -1,15 +1,15 @@
 use std::{any::Any, sync::Arc, fmt::Debug};
 
-use tokio::sync::RwLock;
 use type_uuid::TypeUuid;
 use uuid::Uuid;
 
-use crate::{type_info::TypeInfo, component::{IComponents, self}};
+use crate::{type_info::TypeInfo, component::{IComponents, self}, sync::RwLock};
 
 pub trait IUknownComponent where Self: Sync + Send {
     fn into_boxed(self: Box<Self>) -> Box<dyn Any + Sync + Send>;
     fn type_info(&self) -> TypeInfo;
     fn component_uuid(&self) -> Uuid;
+    fn component_size(&self) -> usize;
     fn new_components_array(&self) -> Arc<RwLock<dyn IComponents>>;
 }
 
@@ -26,6 +26,10 @@ impl<TComponent: 'static + Sync + Send + TypeUuid + Debug> IUknownComponent for
         Uuid::from_bytes(TComponent::UUID)
     }
 
+    fn component_size(&self) -> usize {
+        std::mem::size_of::<TComponent>()
+    }
+
     fn new_components_array(&self) -> Arc<RwLock<dyn IComponents>> {
         Arc::new(RwLock::new(component::new::<TComponent>()))
     }
@@ -41,23 +45,36 @@ impl IntoComponentsInfo for () {
     }
 }
 
-impl<T1: 'static + Sync + Send + TypeUuid + Debug> IntoComponentsInfo for (T1,) {
+impl IntoComponentsInfo for Vec<Box<dyn IUknownComponent>> {
     fn into_components_info(self) -> Vec<Box<dyn IUknownComponent>> {
-        let (component1, ): (T1,) = self;
-        vec![Box::new(component1)]
+        self
     }
 }
 
-impl<T1: 'static + Sync + Send + TypeUuid + Debug, T2: 'static + Sync + Send + TypeUuid + Debug> IntoComponentsInfo for (T1, T2,) {
-    fn into_components_info(self) -> Vec<Box<dyn IUknownComponent>> {
-        let (component1, component2): (T1, T2,) = self;
-        vec![Box::new(component1), Box::new(component2)]
-    }
+macro_rules! impl_into_components_info {
+    ($($component_type:ident $component:ident),+) => {
+        impl<$($component_type: 'static + Sync + Send + TypeUuid + Debug),+> IntoComponentsInfo for ($($component_type,)+) {
+            fn into_components_info(self) -> Vec<Box<dyn IUknownComponent>> {
+                let ($($component,)+): ($($component_type,)+) = self;
+                vec![$(Box::new($component),)+]
+            }
+        }
+    };
 }
 
-impl<T1: 'static + Sync + Send + TypeUuid + Debug, T2: 'static + Sync + Send + TypeUuid + Debug, T3: 'static + Sync + Send + TypeUuid + Debug> IntoComponentsInfo for (T1, T2, T3,) {
-    fn into_components_info(self) -> Vec<Box<dyn IUknownComponent>> {
-        let (component1, component2, component3): (T1, T2, T3,) = self;
-        vec![Box::new(component1), Box::new(component2), Box::new(component3)]
-    }
-}
\ No newline at end of file
+impl_into_components_info!(T1 component1);
+impl_into_components_info!(T1 component1, T2 component2);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6, T7 component7);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6, T7 component7, T8 component8);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6, T7 component7, T8 component8, T9 component9);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6, T7 component7, T8 component8, T9 component9, T10 component10);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6, T7 component7, T8 component8, T9 component9, T10 component10, T11 component11);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6, T7 component7, T8 component8, T9 component9, T10 component10, T11 component11, T12 component12);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6, T7 component7, T8 component8, T9 component9, T10 component10, T11 component11, T12 component12, T13 component13);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6, T7 component7, T8 component8, T9 component9, T10 component10, T11 component11, T12 component12, T13 component13, T14 component14);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6, T7 component7, T8 component8, T9 component9, T10 component10, T11 component11, T12 component12, T13 component13, T14 component14, T15 component15);
+impl_into_components_info!(T1 component1, T2 component2, T3 component3, T4 component4, T5 component5, T6 component6, T7 component7, T8 component8, T9 component9, T10 component10, T11 component11, T12 component12, T13 component13, T14 component14, T15 component15, T16 component16);
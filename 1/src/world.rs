@@ -1,42 +1,286 @@
-use std::{collections::{HashMap, BTreeSet}, any::Any, sync::Arc, future::Future, fmt::Debug};
+use std::{collections::{HashMap, BTreeSet}, any::Any, sync::{Arc, atomic::{AtomicU64, Ordering}}, future::Future, fmt::Debug, marker::PhantomData, pin::pin};
 
-// use async_lock::{RwLock, futures::{Write, Read}, RwLockWriteGuard, RwLockReadGuard};
-use tokio::sync::{RwLock, RwLockWriteGuard, RwLockMappedWriteGuard, RwLockReadGuard};
 use futures::future::BoxFuture;
 use itertools::Itertools;
 use type_uuid::TypeUuid;
 use uuid::Uuid;
 
-use crate::{archetype::{Archetype, self}, component::{Components, IComponents, self}, entity::{EntityId, self}, unknown_component::IntoComponentsInfo};
+use crate::{archetype::{Archetype, ArchetypeId, ArchetypeEdge, EdgeKind, self}, component::{Components, self}, chunk::{ComponentsChunk, ChunkTicks, ChunkSizing, self}, entity::{EntityId, Entities, self}, event::{Events, IEvents, self}, unknown_component::{IntoComponentsInfo, IUknownComponent}, type_info::TypeInfo, system::ComponentAccess, registry::{ComponentId, ComponentRegistry, self}, sync::{RwLock, MappedReadGuard, MappedWriteGuard, self}};
 
 
 #[derive(Debug, Default)]
 pub struct World {
-    archetypes: HashMap<BTreeSet<Uuid>, Archetype>,
-    components: HashMap<Uuid, Arc<RwLock<dyn IComponents>>>
+    // архетипы лежат по номеру ArchetypeId, поиск по набору компонентов идет через сигнатуру
+    archetypes: Vec<Archetype>,
+    archetype_ids: HashMap<registry::Signature, ArchetypeId>,
+    component_ids: ComponentRegistry,
+    // архетипы с данным компонентом по номеру ComponentId, в порядке создания
+    component_archetypes: Vec<Vec<ArchetypeId>>,
+    entities: Entities,
+    // глобальные синглтоны, не привязанные к сущностям; блокируются так же, как колонки
+    resources: HashMap<Uuid, Arc<RwLock<dyn Any + Sync + Send>>>,
+    events: HashMap<Uuid, Arc<RwLock<dyn IEvents>>>,
+    change_tick: AtomicU64,
+    // размер чанков для новых архетипов; у уже созданных архетипов вместимость не меняется
+    chunk_sizing: ChunkSizing,
+    component_chunk_rows: HashMap<Uuid, usize>,
 }
 
-pub fn archetypes<'world: 'arch, 'arch>(world: &'world World, keys: &Vec<BTreeSet<Uuid>>) -> Vec<&'arch Archetype> {
-    keys.iter()
-        .map(|uuids| world.archetypes.get(uuids))
-        .flatten()
+#[derive(Debug, Clone, Copy)]
+pub enum AccessError {
+    // колонка, ресурс или канал событий уже заблокирован несовместимо; component - тип заблокированного
+    Contended { component: TypeInfo },
+    // обязательного компонента, ресурса или канала событий нет в мире
+    Missing { component: TypeInfo },
+    // компонент доступен только на чтение: EntityId меняется лишь структурными изменениями мира
+    ReadOnly { component: TypeInfo },
+    // тип повторяется в наборе доступа: одну колонку нельзя выдать дважды
+    Duplicate { component: TypeInfo },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntityLocation {
+    pub archetype: ArchetypeId,
+    pub chunk: usize,
+    pub row: usize,
+}
+
+pub fn archetypes<'world: 'arch, 'arch>(world: &'world World, archetype_ids: &[ArchetypeId]) -> Vec<&'arch Archetype> {
+    archetype_ids.iter()
+        .filter_map(|archetype_id| archetype(world, *archetype_id))
         .collect_vec()
 }
 
-// pub fn empty_archetype(world: &mut World) -> &mut Archetype {
-//     if let Some(archetype) = world.archetypes.iter_mut().find(|x| archetype::is_empty(x)) {
-//         return archetype;
-//     }
+pub fn archetype(world: &World, archetype_id: ArchetypeId) -> Option<&Archetype> {
+    world.archetypes.get(archetype::index(archetype_id) as usize)
+}
+
+// поиск архетипа по набору uuid, для горячих путей есть номера и ребра переходов
+pub fn archetype_id(world: &World, components: &BTreeSet<Uuid>) -> Option<ArchetypeId> {
+    let component_ids = components.iter()
+        .map(|uuid| registry::component_id(&world.component_ids, *uuid))
+        .collect::<Option<Vec<_>>>()?;
+
+    world.archetype_ids.get(&registry::signature(component_ids)).copied()
+}
+
+// архетипы, в которых есть компонент, в порядке создания
+pub fn component_archetypes(world: &World, component_id: ComponentId) -> &[ArchetypeId] {
+    world.component_archetypes.get(registry::index(component_id) as usize)
+        .map(|archetypes| archetypes.as_slice())
+        .unwrap_or_default()
+}
+
+pub fn component_id(world: &World, component_uuid: Uuid) -> Option<ComponentId> {
+    registry::component_id(&world.component_ids, component_uuid)
+}
+
+pub fn component_uuid(world: &World, component_id: ComponentId) -> Option<Uuid> {
+    registry::uuid(&world.component_ids, component_id)
+}
+
+// архетип сущностей, у которых есть только EntityId; None, пока такая сущность ни разу не создавалась
+pub fn empty_archetype(world: &World) -> Option<&Archetype> {
+    archetype(world, archetype_id(world, &archetype::empty_key())?)
+}
+
+// номер архетипа с таким набором компонентов, архетип создается при первом обращении
+fn archetype_entry(world: &mut World, components: impl Iterator<Item = (Uuid, usize)>) -> ArchetypeId {
+    let components = components.collect_vec();
+
+    let signature = registry::signature(components.iter()
+        .map(|(component_uuid, _)| registry::intern(&mut world.component_ids, *component_uuid)));
+
+    if let Some(archetype_id) = world.archetype_ids.get(&signature) {
+        return *archetype_id;
+    }
+
+    let chunk_capacity = archetype_chunk_capacity(world, components.iter().copied());
+    let archetype_id = archetype::from_index(world.archetypes.len() as u32);
+
+    let components_uuid = components.iter()
+        .map(|(component_uuid, _)| *component_uuid)
+        .collect::<BTreeSet<_>>();
+
+    let archetype = if components_uuid == archetype::empty_key() {
+        archetype::new_empty(archetype_id, signature.clone(), chunk_capacity)
+    } else {
+        archetype::new(archetype_id, components_uuid, signature.clone(), chunk_capacity)
+    };
+
+    world.component_archetypes.resize_with(registry::len(&world.component_ids), Vec::new);
+
+    for component_id in registry::ids(&signature) {
+        world.component_archetypes[registry::index(component_id) as usize].push(archetype_id);
+    }
 
-//     world.archetypes.push(archetype::new_empty());
+    world.archetypes.push(archetype);
+    world.archetype_ids.insert(signature, archetype_id);
 
-//     world.archetypes.last_mut().unwrap()
-// }
+    archetype_id
+}
 
 pub async fn add_entity(world: &mut World, components: impl IntoComponentsInfo) -> EntityId {
-    let entity_id = entity::new();
+    let entity_id = entity::alloc(&mut world.entities);
+
+    add_entity_with_id(world, entity_id, components).await;
+
+    entity_id
+}
+
+// все сущности пачки попадают в один архетип: он определяется один раз, колонки блокируются по одному разу,
+// а компоненты переносятся в чанки без упаковки в Box; None, если в наборе есть EntityId или повторяющиеся типы,
+// или если аллокатор выдал идентификатор, который нельзя занять
+pub async fn spawn_batch<TBundle: IBundle>(world: &mut World, bundles: impl IntoIterator<Item = TBundle>) -> Option<Vec<EntityId>> {
+    let entity_uuid = Uuid::from_bytes(EntityId::UUID);
+    let component_uuids = TBundle::component_uuids();
+
+    let archetype_key = component_uuids.iter()
+        .copied()
+        .chain([entity_uuid])
+        .collect::<BTreeSet<_>>();
+
+    if archetype_key.len() != component_uuids.len() + 1 {
+        return None;
+    }
+
+    let bundles = bundles.into_iter();
+
+    let mut columns = TBundle::columns(bundles.size_hint().0);
+    let mut entity_ids = Vec::with_capacity(bundles.size_hint().0);
+
+    // каждый идентификатор занимается сразу после выдачи, чтобы следующий alloc пачки не выдал тот же слот;
+    // настоящее место строки проставляется после записи колонок
+    for bundle in bundles {
+        let entity_id = entity::alloc(&mut world.entities);
+
+        if !entity::place(&mut world.entities, entity_id, EntityLocation::default()) {
+            for entity_id in entity_ids {
+                entity::free(&mut world.entities, entity_id);
+            }
+
+            return None;
+        }
+
+        entity_ids.push(entity_id);
+        TBundle::push(bundle, &mut columns);
+    }
+
+    let component_sizes = component_uuids.iter()
+        .copied()
+        .zip(TBundle::component_sizes())
+        .chain([(entity_uuid, std::mem::size_of::<EntityId>())]);
+
+    let archetype_id = archetype_entry(world, component_sizes);
+
+    let change_tick = next_change_tick(world);
+
+    let ranges = extend_column(world, archetype_id, entity_ids.clone(), change_tick).await;
+
+    TBundle::extend(world, archetype_id, columns, change_tick).await;
+
+    let archetype = world.archetypes.get(archetype::index(archetype_id) as usize).unwrap();
+    let mut entity_ids_iter = entity_ids.iter();
+
+    for (chunk_idx, rows) in ranges {
+        let chunk = archetype::chunk_position(archetype, entity_uuid, chunk_idx).unwrap();
+
+        for (row, entity_id) in rows.zip(entity_ids_iter.by_ref()) {
+            entity::set_location(&mut world.entities, *entity_id, EntityLocation {
+                archetype: archetype_id,
+                chunk,
+                row,
+            });
+        }
+    }
+
+    Some(entity_ids)
+}
+
+// типизированная колонка пачки целиком дописывается в чанки архетипа
+async fn extend_column<TComponent>(world: &mut World, archetype_id: ArchetypeId, values: Vec<TComponent>, change_tick: u64) -> Vec<(usize, std::ops::Range<usize>)>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    let component_uuid = Uuid::from_bytes(TComponent::UUID);
+
+    let archetype = world.archetypes.get_mut(archetype::index(archetype_id) as usize).unwrap();
+
+    let components = archetype::column_or_insert_with(archetype, component_uuid, || Arc::new(RwLock::new(component::new::<TComponent>())));
 
-    let components = components.into_components_info();
+    let mut components = components.write().await;
+    components.set_change_tick(change_tick);
+
+    let components = components.as_mut_any().downcast_mut::<Components<TComponent>>().unwrap();
+
+    let ranges = component::extend(components, archetype::chunk_ids(archetype, component_uuid).unwrap(), archetype::chunk_capacity(archetype), values);
+
+    for (chunk_idx, _rows) in ranges.iter() {
+        if archetype::chunk_position(archetype, component_uuid, *chunk_idx).is_none() {
+            archetype::add_chunk_id(archetype, component_uuid, *chunk_idx);
+        }
+    }
+
+    ranges
+}
+
+// размер чанков по умолчанию для всех компонентов
+pub fn set_chunk_sizing(world: &mut World, chunk_sizing: ChunkSizing) {
+    world.chunk_sizing = chunk_sizing;
+}
+
+// размер чанков для конкретного компонента; архетип берет наименьшую вместимость из своих компонентов
+pub fn set_component_chunk_sizing<TComponent: 'static + TypeUuid>(world: &mut World, chunk_sizing: ChunkSizing) {
+    let rows = chunk::rows(chunk_sizing, std::mem::size_of::<TComponent>());
+
+    world.component_chunk_rows.insert(Uuid::from_bytes(TComponent::UUID), rows);
+}
+
+fn archetype_chunk_capacity(world: &World, components: impl Iterator<Item = (Uuid, usize)>) -> usize {
+    components
+        .map(|(component_uuid, component_size)| world.component_chunk_rows.get(&component_uuid)
+            .copied()
+            .unwrap_or_else(|| chunk::rows(world.chunk_sizing, component_size)))
+        .min()
+        .unwrap_or_else(|| chunk::rows(world.chunk_sizing, 0))
+}
+
+// сущность без компонентов, их можно навесить позже через insert_component
+pub async fn spawn_empty(world: &mut World) -> EntityId {
+    add_entity(world, ()).await
+}
+
+// идентификатор для сущности, которая появится позже; его обязательно занять через add_entity_with_id,
+// иначе слот пропадет. буфер команд пользуется reservation
+pub fn reserve_entity(world: &World) -> EntityId {
+    entity::reserve(&world.entities)
+}
+
+pub fn entities_snapshot(world: &World) -> entity::EntitiesSnapshot {
+    entity::snapshot(&world.entities)
+}
+
+// поколения и свободные слоты из снимка, до того как в мир попадут сущности; false, если мир уже не пуст
+pub fn restore_entities(world: &mut World, snapshot: entity::EntitiesSnapshot) -> bool {
+    entity::restore(&mut world.entities, snapshot)
+}
+
+// резерв, который вернет слот миру, если его бросят, например вместе с неприменённым буфером команд
+pub fn reservation(world: &World) -> entity::Reservation {
+    entity::reservation(&world.entities)
+}
+
+// идентификатор выдан заранее: зарезервирован или загружен из снимка;
+// false, если такая сущность уже есть или идентификатор устарел
+pub async fn add_entity_with_id(world: &mut World, entity_id: EntityId, components: impl IntoComponentsInfo) -> bool {
+    if !entity::place(&mut world.entities, entity_id, EntityLocation::default()) {
+        return false;
+    }
+
+    let mut components = components.into_components_info();
+
+    // повтор типа может стоять где угодно в наборе: после сортировки повторы соседние, остается первый из них
+    components.sort_by_key(|x| x.component_uuid());
 
     let mut components = components.into_iter()
         .dedup_by(|c1, c2| c1.component_uuid() == c2.component_uuid())
@@ -45,177 +289,1690 @@ pub async fn add_entity(world: &mut World, components: impl IntoComponentsInfo)
 
     components.push(Box::new(entity_id));
 
-    let components_uuid = components.iter()
-        .map(|x| x.component_uuid())
-        .collect::<BTreeSet<_>>();
+    let archetype_id = archetype_entry(world, components.iter().map(|x| (x.component_uuid(), x.component_size())));
 
-    let archetype = world.archetypes.entry(components_uuid.clone())
-        .or_insert_with(|| archetype::new(components_uuid));
+    let location = insert_row(world, archetype_id, components.into_iter().map(|x| (x, None)).collect()).await;
 
-    for component in components {
-        let component_uuid = component.component_uuid();
+    entity::set_location(&mut world.entities, entity_id, location);
+
+    true
+}
 
-        let components = world.components.entry(component.component_uuid())
-            .or_insert_with(|| component.new_components_array());
+pub async fn remove_entity(world: &mut World, entity_id: EntityId) -> bool {
+    let Some(location) = entity::free(&mut world.entities, entity_id) else {
+        return false;
+    };
 
-        let mut components_read_guard = components.write().await;
+    // компоненты дропаются здесь же
+    remove_row(world, &location).await;
 
-        let boxed_component = component.into_boxed();
+    true
+}
 
-        let chunk_ids = archetype::chunk_ids(archetype, component_uuid).unwrap();
+pub async fn insert_component<TComponent>(world: &mut World, entity_id: EntityId, component: TComponent) -> bool
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    let component_uuid = Uuid::from_bytes(TComponent::UUID);
 
-        let result = components_read_guard.push(boxed_component, chunk_ids);
+    if component_uuid == Uuid::from_bytes(EntityId::UUID) {
+        return false;
+    }
 
-        let push_action = result.unwrap();
+    let Some(location) = entity::location(&world.entities, entity_id).cloned() else {
+        return false;
+    };
 
-        match push_action {
-            crate::component::PushComponentAction::NewChunk { address } => {
-                archetype::add_chunk_id(archetype, component_uuid, component::chunk_idx(&address));
-                // TODO: добавлять в lookup карты в архетип и мир
-            },
-            crate::component::PushComponentAction::PushToChunk { address } => {
-                // если добавили компонент в существующий чане, нет смысла обновлять список чанков
-                // TODO: добавлять в lookup карты в архетип и мир
-            },
-        }
+    let component_id = registry::intern(&mut world.component_ids, component_uuid);
+    let archetype = archetype(world, location.archetype).unwrap();
+
+    // компонент уже есть у сущности, переезд в другой архетип не нужен
+    if registry::contains(archetype::signature(archetype), component_id) {
+        let chunk_id = archetype::chunk_ids(archetype, component_uuid).unwrap()[location.chunk];
+
+        let change_tick = next_change_tick(world);
+
+        let mut components = write_column::<TComponent>(archetype, change_tick).await.unwrap();
+
+        chunk::replace(component::chunk_mut(&mut components, chunk_id).unwrap(), location.row, component).unwrap();
+
+        return true;
     }
 
-    entity_id
+    let mut components = remove_row(world, &location).await
+        .into_iter()
+        .map(|(component, ticks)| (component, Some(ticks)))
+        .collect_vec();
+
+    components.push((Box::new(component), None));
+
+    let target = migration_target(world, location.archetype, EdgeKind::Add, component_id, &components);
+    let location = insert_row(world, target, components).await;
+
+    entity::set_location(&mut world.entities, entity_id, location);
+
+    true
 }
 
-pub fn query(world: &World, filter: impl Fn(&Archetype) -> bool) -> Vec<BTreeSet<Uuid>> {
-    world.archetypes.iter() 
-        .filter(|(_key, archetype)| filter(archetype))
-        .map(|(key, _archetype)| key.clone())
-        .collect()
+pub async fn remove_component<TComponent>(world: &mut World, entity_id: EntityId) -> Option<TComponent>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    let component_uuid = Uuid::from_bytes(TComponent::UUID);
+
+    if component_uuid == Uuid::from_bytes(EntityId::UUID) {
+        return None;
+    }
+
+    let location = entity::location(&world.entities, entity_id)?.clone();
+    let component_id = registry::component_id(&world.component_ids, component_uuid)?;
+
+    if !registry::contains(archetype::signature(archetype(world, location.archetype).unwrap()), component_id) {
+        return None;
+    }
+
+    let (removed, components): (Vec<_>, Vec<_>) = remove_row(world, &location).await
+        .into_iter()
+        .map(|(component, ticks)| (component, Some(ticks)))
+        .partition(|(x, _)| x.component_uuid() == component_uuid);
+
+    let target = migration_target(world, location.archetype, EdgeKind::Remove, component_id, &components);
+    let location = insert_row(world, target, components).await;
+
+    entity::set_location(&mut world.entities, entity_id, location);
+
+    let removed = removed.into_iter().next()?.0.into_boxed().downcast::<TComponent>().ok()?;
+
+    Some(*removed)
 }
 
-pub async fn get<'access, TAccessQuery>(world: &'access World) -> Option<TAccessQuery::TAccess<'access>>
+// возвращает предыдущее значение ресурса, если он уже был
+pub async fn insert_resource<TResource>(world: &mut World, resource: TResource) -> Option<TResource>
 where
-    TAccessQuery: IAccessManager,
+    TResource: 'static + Sync + Send + Debug + TypeUuid,
 {
-    TAccessQuery::extract(world).await
+    let resource_uuid = Uuid::from_bytes(TResource::UUID);
+
+    let Some(current) = world.resources.get(&resource_uuid) else {
+        world.resources.insert(resource_uuid, Arc::new(RwLock::new(resource)));
+        return None;
+    };
+
+    let mut current = current.write().await;
+    let current = current.downcast_mut::<TResource>().unwrap();
+
+    Some(std::mem::replace(current, resource))
 }
 
-pub trait IAccessManager {
-    type TAccess<'access>: 'access;
+pub async fn resource<TResource>(world: &World) -> Option<ReadResource<'_, TResource>>
+where
+    TResource: 'static + Sync + Send + Debug + TypeUuid,
+{
+    let guard = world.resources.get(&Uuid::from_bytes(TResource::UUID))?.read().await;
 
-    async fn extract<'access>(world: &'access World) -> Option<Self::TAccess<'access>>;
+    Some(sync::map_read(guard, |guard| guard.downcast_ref::<TResource>().unwrap()))
 }
 
-pub trait IAccessVariant {
-    type TAccess<'access>: 'access;
+pub async fn resource_mut<TResource>(world: &World) -> Option<WriteResource<'_, TResource>>
+where
+    TResource: 'static + Sync + Send + Debug + TypeUuid,
+{
+    let guard = world.resources.get(&Uuid::from_bytes(TResource::UUID))?.write().await;
 
-    async fn extract<'access>(components: &'access Arc<RwLock<dyn IComponents>>) -> Self::TAccess<'access>;
+    Some(sync::map_write(guard, |guard| guard.downcast_mut::<TResource>().unwrap()))
+}
 
-    fn type_uuid() -> Uuid;
+pub fn try_resource<TResource>(world: &World) -> Result<ReadResource<'_, TResource>, AccessError>
+where
+    TResource: 'static + Sync + Send + Debug + TypeUuid,
+{
+    let resource = world.resources.get(&Uuid::from_bytes(TResource::UUID))
+        .ok_or(AccessError::Missing { component: TypeInfo::from_type::<TResource>() })?;
+
+    let guard = sync::try_read(resource.as_ref())
+        .ok_or(AccessError::Contended { component: TypeInfo::from_type::<TResource>() })?;
+
+    Ok(sync::map_read(guard, |guard| guard.downcast_ref::<TResource>().unwrap()))
 }
 
-impl<T: 'static + Sync + Send + Debug> IAccessVariant for &mut T
-where T: TypeUuid
+pub fn try_resource_mut<TResource>(world: &World) -> Result<WriteResource<'_, TResource>, AccessError>
+where
+    TResource: 'static + Sync + Send + Debug + TypeUuid,
 {
-    type TAccess<'access> = RwLockMappedWriteGuard<'access, Components<T>>;
-    
-    async fn extract<'access>(components: &'access Arc<RwLock<dyn IComponents>>) -> Self::TAccess<'access> {
-        let guard = components.write().await;
-        let components = RwLockWriteGuard::map(guard, |guard| guard.as_mut_any().downcast_mut::<Components<T>>().unwrap());
+    let resource = world.resources.get(&Uuid::from_bytes(TResource::UUID))
+        .ok_or(AccessError::Missing { component: TypeInfo::from_type::<TResource>() })?;
 
-        components
-    }
+    let guard = sync::try_write(resource.as_ref())
+        .ok_or(AccessError::Contended { component: TypeInfo::from_type::<TResource>() })?;
 
-    fn type_uuid() -> Uuid {
-        Uuid::from_bytes(T::UUID)
-    }
+    Ok(sync::map_write(guard, |guard| guard.downcast_mut::<TResource>().unwrap()))
 }
 
-impl<T: 'static + Sync + Send + Debug> IAccessVariant for &T
-where T: TypeUuid
+pub fn contains_resource<TResource: TypeUuid>(world: &World) -> bool {
+    world.resources.contains_key(&Uuid::from_bytes(TResource::UUID))
+}
+
+// канал событий нужно завести заранее: отправлять в него можно и из систем, у которых есть только &World
+pub fn register_event<TEvent>(world: &mut World)
+where
+    TEvent: 'static + Sync + Send + Debug + TypeUuid,
+{
+    world.events.entry(Uuid::from_bytes(TEvent::UUID))
+        .or_insert_with(|| Arc::new(RwLock::new(event::new::<TEvent>())));
+}
+
+// false, если канал не заведен
+pub async fn send_event<TEvent>(world: &World, event: TEvent) -> bool
+where
+    TEvent: 'static + Sync + Send + Debug + TypeUuid,
+{
+    let Some(mut events) = events_mut::<TEvent>(world).await else {
+        return false;
+    };
+
+    event::send(&mut events, event);
+
+    true
+}
+
+pub async fn events<TEvent>(world: &World) -> Option<ReadEvents<'_, TEvent>>
+where
+    TEvent: 'static + Sync + Send + Debug + TypeUuid,
+{
+    let guard = world.events.get(&Uuid::from_bytes(TEvent::UUID))?.read().await;
+
+    Some(sync::map_read(guard, |guard| guard.as_any().downcast_ref::<Events<TEvent>>().unwrap()))
+}
+
+pub async fn events_mut<TEvent>(world: &World) -> Option<WriteEvents<'_, TEvent>>
+where
+    TEvent: 'static + Sync + Send + Debug + TypeUuid,
+{
+    let guard = world.events.get(&Uuid::from_bytes(TEvent::UUID))?.write().await;
+
+    Some(sync::map_write(guard, |guard| guard.as_mut_any().downcast_mut::<Events<TEvent>>().unwrap()))
+}
+
+pub fn try_events<TEvent>(world: &World) -> Result<ReadEvents<'_, TEvent>, AccessError>
+where
+    TEvent: 'static + Sync + Send + Debug + TypeUuid,
+{
+    let events = world.events.get(&Uuid::from_bytes(TEvent::UUID))
+        .ok_or(AccessError::Missing { component: TypeInfo::from_type::<TEvent>() })?;
+
+    let guard = sync::try_read(events.as_ref())
+        .ok_or(AccessError::Contended { component: TypeInfo::from_type::<TEvent>() })?;
+
+    Ok(sync::map_read(guard, |guard| guard.as_any().downcast_ref::<Events<TEvent>>().unwrap()))
+}
+
+pub fn try_events_mut<TEvent>(world: &World) -> Result<WriteEvents<'_, TEvent>, AccessError>
+where
+    TEvent: 'static + Sync + Send + Debug + TypeUuid,
 {
-    type TAccess<'access> = RwLockReadGuard<'access, Components<T>>;
+    let events = world.events.get(&Uuid::from_bytes(TEvent::UUID))
+        .ok_or(AccessError::Missing { component: TypeInfo::from_type::<TEvent>() })?;
+
+    let guard = sync::try_write(events.as_ref())
+        .ok_or(AccessError::Contended { component: TypeInfo::from_type::<TEvent>() })?;
+
+    Ok(sync::map_write(guard, |guard| guard.as_mut_any().downcast_mut::<Events<TEvent>>().unwrap()))
+}
+
+// граница кадра для всех каналов, планировщик вызывает её после завершения всех систем
+pub async fn update_events(world: &World) {
+    let mut uuids = world.events.keys().copied().collect_vec();
 
-    async fn extract<'access>(components: &'access Arc<RwLock<dyn IComponents>>) -> Self::TAccess<'access> {
-        let guard = components.read().await;
-        let components = RwLockReadGuard::map(guard, |guard| guard.as_any().downcast_ref::<Components<T>>().unwrap());
+    uuids.sort();
 
-        components
+    for uuid in uuids {
+        world.events.get(&uuid).unwrap().write().await.update();
     }
+}
 
-    fn type_uuid() -> Uuid {
-        Uuid::from_bytes(T::UUID)
+pub fn change_tick(world: &World) -> u64 {
+    world.change_tick.load(Ordering::Acquire)
+}
+
+// каждая выдача доступа к колонкам получает свой тик, чтобы изменения можно было упорядочить
+pub fn next_change_tick(world: &World) -> u64 {
+    world.change_tick.fetch_add(1, Ordering::AcqRel) + 1
+}
+
+// false и для удаленных сущностей, и для устаревших идентификаторов, чей слот уже занят новой сущностью
+pub fn is_alive(world: &World, entity_id: EntityId) -> bool {
+    entity::is_alive(&world.entities, entity_id)
+}
+
+pub fn location(world: &World, entity_id: EntityId) -> Option<&EntityLocation> {
+    entity::location(&world.entities, entity_id)
+}
+
+// архетип, в который переезжает сущность при добавлении или удалении компонента; components - её компоненты после переезда.
+// переход запоминается в обе стороны, повторный переезд того же вида обходится без поиска по сигнатуре
+fn migration_target(world: &mut World, source: ArchetypeId, kind: EdgeKind, component_id: ComponentId, components: &[(Box<dyn IUknownComponent>, Option<ChunkTicks>)]) -> ArchetypeId {
+    if let Some(target) = archetype::edge(archetype(world, source).unwrap(), kind, component_id) {
+        return target;
+    }
+
+    let target = archetype_entry(world, components.iter().map(|(x, _)| (x.component_uuid(), x.component_size())));
+
+    let reverse = match kind {
+        EdgeKind::Add => EdgeKind::Remove,
+        EdgeKind::Remove => EdgeKind::Add,
+    };
+
+    archetype::set_edge(&mut world.archetypes[archetype::index(source) as usize], kind, component_id, target);
+
+    let target_archetype = &mut world.archetypes[archetype::index(target) as usize];
+
+    if archetype::edge(target_archetype, reverse, component_id).is_none() {
+        archetype::set_edge(target_archetype, reverse, component_id, source);
     }
+
+    target
 }
 
-pub type WriteComponents<'access, T> = RwLockMappedWriteGuard<'access, Components<T>>;
-pub type ReadComponents<'access, T> = RwLockReadGuard<'access, Components<T>>;
+// граф переходов между архетипами для отладки: ребра каждого архетипа, архетипы в порядке номеров
+pub fn archetype_graph(world: &World) -> Vec<(ArchetypeId, Vec<ArchetypeEdge>)> {
+    world.archetypes.iter()
+        .map(|archetype| (archetype::id(archetype), archetype::edges(archetype)))
+        .collect()
+}
 
-impl<T1: IAccessVariant, T2: IAccessVariant> IAccessManager for (T1, T2) {
-    type TAccess<'access> = (T1::TAccess<'access>, T2::TAccess<'access>);
+// кладет строку в архетип archetype_id, его набор компонентов обязан совпадать с components и содержать EntityId;
+// у переезжающих компонентов тики исходного чанка, None - компонент новый и получает текущий тик
+async fn insert_row(world: &mut World, archetype_id: ArchetypeId, components: Vec<(Box<dyn IUknownComponent>, Option<ChunkTicks>)>) -> EntityLocation {
+    let change_tick = next_change_tick(world);
 
-    async fn extract<'world>(world: &'world World) -> Option<Self::TAccess<'world>> {
-        let mut uuids = vec![
-            T1::type_uuid(),
-            T2::type_uuid(),
-        ];
+    let archetype = world.archetypes.get_mut(archetype::index(archetype_id) as usize).unwrap();
+    let chunk_capacity = archetype::chunk_capacity(archetype);
 
-        uuids.sort();
+    let mut entity_address = None;
 
-        let mut components_t1 = None;
-        let mut components_t2 = None;
+    for (component, ticks) in components {
+        let component_uuid = component.component_uuid();
 
-        for uuid in uuids {
-            if T1::type_uuid() == uuid {
-                let component_1 = world.components.get(&T1::type_uuid())?;
-                components_t1 = Some(T1::extract(component_1).await);
-                continue;
-            }
+        let components = archetype::column_or_insert_with(archetype, component_uuid, || component.new_components_array());
 
-            if T2::type_uuid() == uuid {
-                let component_2 = world.components.get(&T2::type_uuid())?;
-                components_t2 = Some(T2::extract(&component_2).await);
-                continue;
-            }
+        let mut components_read_guard = components.write().await;
+        components_read_guard.set_change_tick(change_tick);
+
+        let boxed_component = component.into_boxed();
+
+        let chunk_ids = archetype::chunk_ids(archetype, component_uuid).unwrap();
+
+        let result = components_read_guard.push(boxed_component, chunk_ids, chunk_capacity, ticks);
+
+        let push_action = result.unwrap();
+
+        let address = match push_action {
+            crate::component::PushComponentAction::NewChunk { address } => {
+                archetype::add_chunk_id(archetype, component_uuid, component::chunk_idx(&address));
+                address
+            },
+            crate::component::PushComponentAction::PushToChunk { address } => {
+                // если добавили компонент в существующий чане, нет смысла обновлять список чанков
+                address
+            },
+        };
+
+        if component_uuid == Uuid::from_bytes(EntityId::UUID) {
+            entity_address = Some(address);
         }
+    }
 
-        return Some((components_t1?, components_t2?));
+    // колонки архетипа заполняются синхронно, поэтому позиция строки у всех компонентов одна и та же
+    let entity_address = entity_address.unwrap();
+
+    EntityLocation {
+        chunk: archetype::chunk_position(archetype, Uuid::from_bytes(EntityId::UUID), component::chunk_idx(&entity_address)).unwrap(),
+        row: component::component_idx(&entity_address),
+        archetype: archetype_id,
     }
 }
 
-impl<T1: IAccessVariant, T2: IAccessVariant, T3: IAccessVariant> IAccessManager for (T1, T2, T3) {
-    type TAccess<'access> = (T1::TAccess<'access>, T2::TAccess<'access>, T3::TAccess<'access>);
+// вынимает строку из архетипа вместе с тиками её чанков, на её место переезжает последняя строка архетипа
+async fn remove_row(world: &mut World, location: &EntityLocation) -> Vec<(Box<dyn IUknownComponent>, ChunkTicks)> {
+    let change_tick = next_change_tick(world);
 
-    async fn extract<'world>(world: &'world World) -> Option<Self::TAccess<'world>> {
-        let mut uuids = vec![
-            T1::type_uuid(),
-            T2::type_uuid(),
-            T3::type_uuid(),
-        ];
+    let archetype = world.archetypes.get(archetype::index(location.archetype) as usize).unwrap();
 
-        uuids.sort();
+    let mut removed = Vec::with_capacity(archetype::components(archetype).len());
 
-        let mut components_t1 = None;
-        let mut components_t2 = None;
-        let mut components_t3 = None;
+    for component_uuid in archetype::components(archetype).iter() {
+        let chunk_ids = archetype::chunk_ids(archetype, *component_uuid).unwrap();
 
-        for uuid in uuids {
-            if T1::type_uuid() == uuid {
-                let component_1 = world.components.get(&T1::type_uuid())?;
-                components_t1 = Some(T1::extract(component_1).await);
-                continue;
-            }
+        let address = component::address(chunk_ids[location.chunk], location.row);
 
-            if T2::type_uuid() == uuid {
-                let component_2 = world.components.get(&T2::type_uuid())?;
-                components_t2 = Some(T2::extract(&component_2).await);
-                continue;
-            }
+        let mut components_write_guard = archetype::column(archetype, *component_uuid).unwrap().write().await;
+        components_write_guard.set_change_tick(change_tick);
 
-            if T3::type_uuid() == uuid {
-                let component_3 = world.components.get(&T3::type_uuid())?;
-                components_t3 = Some(T3::extract(&component_3).await);
-                continue;
-            }
-        }
+        // тики снимаются до удаления: swap_remove помечает чанк измененным
+        let ticks = components_write_guard.chunk_ticks(component::chunk_idx(&address)).unwrap();
 
-        return Some((components_t1?, components_t2?, components_t3?));
+        removed.push((components_write_guard.swap_remove(&address, chunk_ids).unwrap(), ticks));
     }
-}
\ No newline at end of file
+
+    let chunk_ids = archetype::chunk_ids_by_type::<EntityId>(archetype).unwrap();
+
+    let entity_ids = read_column::<EntityId>(archetype).await.unwrap();
+
+    let moved_entity_id = component::chunk(&entity_ids, chunk_ids[location.chunk])
+        .and_then(|chunk| chunk::components(chunk).get(location.row));
+
+    if let Some(moved_entity_id) = moved_entity_id {
+        entity::set_location(&mut world.entities, *moved_entity_id, location.clone());
+    }
+
+    removed
+}
+
+// типизированная колонка архетипа на чтение; None, если компонента в архетипе нет
+pub async fn read_column<TComponent>(archetype: &Archetype) -> Option<ReadComponents<'_, TComponent>>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    let guard = archetype::column(archetype, Uuid::from_bytes(TComponent::UUID))?.read().await;
+
+    Some(sync::map_read(guard, |guard| guard.as_any().downcast_ref::<Components<TComponent>>().unwrap()))
+}
+
+// выданные через колонку на запись чанки помечаются тиком change_tick
+pub async fn write_column<TComponent>(archetype: &Archetype, change_tick: u64) -> Option<WriteComponents<'_, TComponent>>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    let mut guard = archetype::column(archetype, Uuid::from_bytes(TComponent::UUID))?.write().await;
+    guard.set_change_tick(change_tick);
+
+    Some(sync::map_write(guard, |guard| guard.as_mut_any().downcast_mut::<Components<TComponent>>().unwrap()))
+}
+
+// как read_column, но без ожидания; Ok(None), если компонента в архетипе нет
+pub fn try_read_column<TComponent>(archetype: &Archetype) -> Result<Option<ReadComponents<'_, TComponent>>, AccessError>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    let Some(column) = archetype::column(archetype, Uuid::from_bytes(TComponent::UUID)) else {
+        return Ok(None);
+    };
+
+    let guard = sync::try_read(column.as_ref())
+        .ok_or(AccessError::Contended { component: TypeInfo::from_type::<TComponent>() })?;
+
+    Ok(Some(sync::map_read(guard, |guard| guard.as_any().downcast_ref::<Components<TComponent>>().unwrap())))
+}
+
+pub fn try_write_column<TComponent>(archetype: &Archetype, change_tick: u64) -> Result<Option<WriteComponents<'_, TComponent>>, AccessError>
+where
+    TComponent: 'static + Sync + Send + TypeUuid + Debug,
+{
+    let Some(column) = archetype::column(archetype, Uuid::from_bytes(TComponent::UUID)) else {
+        return Ok(None);
+    };
+
+    let mut guard = sync::try_write(column.as_ref())
+        .ok_or(AccessError::Contended { component: TypeInfo::from_type::<TComponent>() })?;
+
+    guard.set_change_tick(change_tick);
+
+    Ok(Some(sync::map_write(guard, |guard| guard.as_mut_any().downcast_mut::<Components<TComponent>>().unwrap())))
+}
+
+// есть ли в мире архетип, в котором есть все перечисленные компоненты
+pub fn has_archetype_with(world: &World, components: impl IntoIterator<Item = Uuid>) -> bool {
+    let Some(component_ids) = components.into_iter()
+        .map(|uuid| registry::component_id(&world.component_ids, uuid))
+        .collect::<Option<Vec<_>>>() else {
+        return false;
+    };
+
+    let Some(candidates) = component_ids.iter()
+        .map(|component_id| component_archetypes(world, *component_id))
+        .min_by_key(|archetypes| archetypes.len()) else {
+        return !world.archetypes.is_empty();
+    };
+
+    let required = registry::signature(component_ids);
+
+    archetypes(world, candidates).into_iter()
+        .any(|archetype| registry::is_superset(archetype::signature(archetype), &required))
+}
+
+pub fn filter_archetypes(world: &World, filter: impl Fn(&Archetype) -> bool) -> Vec<ArchetypeId> {
+    world.archetypes.iter()
+        .filter(|archetype| filter(archetype))
+        .map(archetype::id)
+        .collect()
+}
+
+pub async fn query<'world, TAccessQuery, TFilter>(world: &'world World) -> Option<Query<'world, TAccessQuery, TFilter>>
+where
+    TAccessQuery: IAccessManager,
+    TFilter: IQueryFilter,
+{
+    query_since::<TAccessQuery, TFilter>(world, 0).await
+}
+
+// Added/Changed пропускают чанки, не менявшиеся после last_change_tick
+pub async fn query_since<'world, TAccessQuery, TFilter>(world: &'world World, last_change_tick: u64) -> Option<Query<'world, TAccessQuery, TFilter>>
+where
+    TAccessQuery: IAccessManager,
+    TFilter: IQueryFilter,
+{
+    query_with_state(&mut query_state::<TAccessQuery, TFilter>(), world, last_change_tick).await
+}
+
+// архетипы из мира не удаляются, поэтому их число служит поколением: всё, что старше, уже проверено
+pub fn archetype_generation(world: &World) -> usize {
+    world.archetypes.len()
+}
+
+// подходящие выборке архетипы, запомненные между запусками; состояние привязано к одному миру
+pub struct QueryState<TAccessQuery: IAccessManager, TFilter: IQueryFilter = ()> {
+    archetypes: Vec<ArchetypeId>,
+    // сколько архетипов мира уже проверено
+    generation: usize,
+    // None, пока хотя бы один из обязательных компонентов не появился в мире
+    required: Option<registry::Signature>,
+    access: PhantomData<(TAccessQuery, TFilter)>,
+}
+
+// тики чанков фильтр читает через доступ самой выборки, отдельных блокировок он не берет;
+// фильтр по тикам компонента вне доступа - ошибка в типах выборки, а не пустой результат, поэтому он отвергается сразу
+pub fn query_state<TAccessQuery: IAccessManager, TFilter: IQueryFilter>() -> QueryState<TAccessQuery, TFilter> {
+    let type_uuids = TAccessQuery::type_uuids();
+
+    assert!(
+        TFilter::tick_uuids().iter().all(|uuid| type_uuids.contains(uuid)),
+        "filter {} checks ticks of a component that is not in the access tuple {}",
+        std::any::type_name::<TFilter>(),
+        std::any::type_name::<TAccessQuery>(),
+    );
+
+    QueryState {
+        archetypes: vec![],
+        generation: 0,
+        required: None,
+        access: PhantomData,
+    }
+}
+
+// проверяет только архетипы, созданные после прошлого обновления
+pub fn update_query_state<TAccessQuery: IAccessManager, TFilter: IQueryFilter>(state: &mut QueryState<TAccessQuery, TFilter>, world: &World) {
+    let generation = archetype_generation(world);
+
+    if state.generation == generation {
+        return;
+    }
+
+    // архетипы, созданные до регистрации компонента, не могут его содержать, так что пропущенные архетипы не теряются
+    if state.required.is_none() {
+        state.required = TAccessQuery::required_uuids().iter()
+            .map(|uuid| registry::component_id(&world.component_ids, *uuid))
+            .collect::<Option<Vec<_>>>()
+            .map(registry::signature);
+    }
+
+    if let Some(required) = state.required.as_ref() {
+        // кандидаты берутся из списка самого редкого обязательного компонента, остальные проверяются по сигнатуре
+        let candidates = registry::ids(required)
+            .map(|component_id| component_archetypes(world, component_id))
+            .min_by_key(|archetypes| archetypes.len());
+
+        let candidates = match candidates {
+            Some(archetype_ids) => {
+                let start = archetype_ids.partition_point(|archetype_id| (archetype::index(*archetype_id) as usize) < state.generation);
+                archetypes(world, &archetype_ids[start..])
+            },
+            // обязательных компонентов нет, подходит любой архетип
+            None => world.archetypes[state.generation..].iter().collect(),
+        };
+
+        let archetypes = candidates.into_iter()
+            .filter(|archetype| registry::is_superset(archetype::signature(archetype), required))
+            .filter(|archetype| TFilter::matches(archetype))
+            .map(archetype::id);
+
+        state.archetypes.extend(archetypes);
+    }
+
+    state.generation = generation;
+}
+
+pub fn query_state_archetypes<TAccessQuery: IAccessManager, TFilter: IQueryFilter>(state: &QueryState<TAccessQuery, TFilter>) -> &[ArchetypeId] {
+    &state.archetypes
+}
+
+// выборка по сохраненному состоянию, система держит его у себя между кадрами
+pub async fn query_with_state<'world, TAccessQuery, TFilter>(state: &mut QueryState<TAccessQuery, TFilter>, world: &'world World, last_change_tick: u64) -> Option<Query<'world, TAccessQuery, TFilter>>
+where
+    TAccessQuery: IAccessManager,
+    TFilter: IQueryFilter,
+{
+    update_query_state(state, world);
+
+    let archetypes = archetypes(world, &state.archetypes);
+
+    let change_tick = next_change_tick(world);
+
+    let access = TAccessQuery::extract(world, &archetypes, change_tick).await?;
+
+    // EntityId пишется только при структурных изменениях под &mut World, а &mut EntityId доступ отвергает,
+    // так что блокировка на чтение после колонок доступа не нарушает порядок
+    let entity_ids = <&EntityId>::extract(world, &archetypes, change_tick).await?
+        .into_iter()
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(Query {
+        archetypes,
+        entity_ids,
+        access,
+        last_change_tick,
+        change_tick,
+        filter: PhantomData,
+    })
+}
+
+pub struct Query<'world, TAccessQuery: IAccessManager, TFilter: IQueryFilter = ()> {
+    archetypes: Vec<&'world Archetype>,
+    // по колонке EntityId определяется число строк в чанке, даже если все остальные колонки опциональны;
+    // колонки лежат в порядке archetypes
+    entity_ids: Vec<ReadComponents<'world, EntityId>>,
+    access: TAccessQuery::TAccess<'world>,
+    last_change_tick: u64,
+    change_tick: u64,
+    filter: PhantomData<TFilter>,
+}
+
+// тик, которым помечены выданные на запись чанки; система сохраняет его как last_change_tick для следующего запуска
+pub fn query_change_tick<TAccessQuery: IAccessManager, TFilter: IQueryFilter>(query: &Query<TAccessQuery, TFilter>) -> u64 {
+    query.change_tick
+}
+
+impl<'query, 'world, TAccessQuery: IAccessManager, TFilter: IQueryFilter> IntoIterator for &'query mut Query<'world, TAccessQuery, TFilter> {
+    type Item = TAccessQuery::TItem<'query>;
+    type IntoIter = QueryIter<'query, TAccessQuery, TFilter>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        QueryIter {
+            fetch: TAccessQuery::fetch(&mut self.access),
+            archetypes: &self.archetypes,
+            entity_ids: &self.entity_ids,
+            last_change_tick: self.last_change_tick,
+            archetype_idx: 0,
+            chunk_position: 0,
+            rows: None,
+            filter: PhantomData,
+        }
+    }
+}
+
+pub struct QueryIter<'query, TAccessQuery: IAccessManager, TFilter: IQueryFilter = ()> {
+    fetch: TAccessQuery::TFetch<'query>,
+    archetypes: &'query [&'query Archetype],
+    entity_ids: &'query [ReadComponents<'query, EntityId>],
+    last_change_tick: u64,
+    archetype_idx: usize,
+    chunk_position: usize,
+    rows: Option<TAccessQuery::TRows<'query>>,
+    filter: PhantomData<TFilter>,
+}
+
+impl<'query, TAccessQuery: IAccessManager, TFilter: IQueryFilter> Iterator for QueryIter<'query, TAccessQuery, TFilter> {
+    type Item = TAccessQuery::TItem<'query>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.rows.as_mut().and_then(|rows| rows.next()) {
+                return Some(item);
+            }
+
+            let archetype = self.archetypes.get(self.archetype_idx)?;
+
+            let entity_ids_chunk = archetype::chunk_ids_by_type::<EntityId>(archetype)
+                .and_then(|chunk_ids| chunk_ids.get(self.chunk_position))
+                .and_then(|chunk_id| component::chunk(&self.entity_ids[self.archetype_idx], *chunk_id));
+
+            // чанки архетипа кончились, переходим к следующему архетипу
+            let Some(entity_ids_chunk) = entity_ids_chunk else {
+                self.rows = None;
+                self.archetype_idx += 1;
+                self.chunk_position = 0;
+                continue;
+            };
+
+            let archetype_idx = self.archetype_idx;
+            let chunk_position = self.chunk_position;
+            self.chunk_position += 1;
+
+            if !TFilter::is_archetypal() {
+                let fetch = &self.fetch;
+
+                let ticks = |uuid: Uuid| {
+                    let chunk_id = archetype::chunk_ids(archetype, uuid)?.get(chunk_position)?;
+                    TAccessQuery::ticks(fetch, archetype_idx, uuid, *chunk_id)
+                };
+
+                if !TFilter::matches_chunk(archetype, &ticks, self.last_change_tick) {
+                    self.rows = None;
+                    continue;
+                }
+            }
+
+            self.rows = TAccessQuery::rows(&mut self.fetch, archetype_idx, archetype, chunk_position, chunk::len(entity_ids_chunk));
+        }
+    }
+}
+
+pub struct ZipRows<TRows>(TRows);
+
+macro_rules! impl_zip_rows {
+    ($($row:ident $idx:tt),+) => {
+        impl<$($row: Iterator),+> Iterator for ZipRows<($($row,)+)> {
+            type Item = ($($row::Item,)+);
+
+            fn next(&mut self) -> Option<Self::Item> {
+                Some(($(self.0.$idx.next()?,)+))
+            }
+        }
+    };
+}
+
+impl_zip_rows!(R1 0);
+impl_zip_rows!(R1 0, R2 1);
+impl_zip_rows!(R1 0, R2 1, R3 2);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5, R7 6);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5, R7 6, R8 7);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5, R7 6, R8 7, R9 8);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5, R7 6, R8 7, R9 8, R10 9);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5, R7 6, R8 7, R9 8, R10 9, R11 10);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5, R7 6, R8 7, R9 8, R10 9, R11 10, R12 11);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5, R7 6, R8 7, R9 8, R10 9, R11 10, R12 11, R13 12);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5, R7 6, R8 7, R9 8, R10 9, R11 10, R12 11, R13 12, R14 13);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5, R7 6, R8 7, R9 8, R10 9, R11 10, R12 11, R13 12, R14 13, R15 14);
+impl_zip_rows!(R1 0, R2 1, R3 2, R4 3, R5 4, R6 5, R7 6, R8 7, R9 8, R10 9, R11 10, R12 11, R13 12, R14 13, R15 14, R16 15);
+
+pub enum OptionalRows<TRows> {
+    Present(TRows),
+    Missing(usize),
+}
+
+impl<TRows: Iterator> Iterator for OptionalRows<TRows> {
+    type Item = Option<TRows::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            OptionalRows::Present(rows) => rows.next().map(Some),
+            OptionalRows::Missing(0) => None,
+            OptionalRows::Missing(len) => {
+                *len -= 1;
+                Some(None)
+            },
+        }
+    }
+}
+
+pub trait IQueryFilter {
+    // отбор архетипов, для фильтров по тикам - необходимое, но не достаточное условие
+    fn matches(archetype: &Archetype) -> bool;
+
+    // true, если фильтр не смотрит на тики чанков и решается целиком по архетипу
+    fn is_archetypal() -> bool {
+        true
+    }
+
+    fn tick_uuids() -> Vec<Uuid> {
+        vec![]
+    }
+
+    fn matches_chunk(archetype: &Archetype, _ticks: &dyn Fn(Uuid) -> Option<ChunkTicks>, _last_change_tick: u64) -> bool {
+        Self::matches(archetype)
+    }
+}
+
+pub struct With<T>(PhantomData<T>);
+pub struct Without<T>(PhantomData<T>);
+pub struct Not<TFilter>(PhantomData<TFilter>);
+pub struct Or<TFilters>(PhantomData<TFilters>);
+pub struct Added<T>(PhantomData<T>);
+pub struct Changed<T>(PhantomData<T>);
+
+impl IQueryFilter for () {
+    fn matches(_archetype: &Archetype) -> bool {
+        true
+    }
+}
+
+impl<T: 'static + TypeUuid> IQueryFilter for With<T> {
+    fn matches(archetype: &Archetype) -> bool {
+        archetype::has::<T>(archetype)
+    }
+}
+
+impl<T: 'static + TypeUuid> IQueryFilter for Without<T> {
+    fn matches(archetype: &Archetype) -> bool {
+        !archetype::has::<T>(archetype)
+    }
+}
+
+impl<TFilter: IQueryFilter> IQueryFilter for Not<TFilter> {
+    fn matches(archetype: &Archetype) -> bool {
+        // по архетипу нельзя отрицать фильтр, который еще будет проверять чанки
+        !TFilter::is_archetypal() || !TFilter::matches(archetype)
+    }
+
+    fn is_archetypal() -> bool {
+        TFilter::is_archetypal()
+    }
+
+    fn tick_uuids() -> Vec<Uuid> {
+        TFilter::tick_uuids()
+    }
+
+    fn matches_chunk(archetype: &Archetype, ticks: &dyn Fn(Uuid) -> Option<ChunkTicks>, last_change_tick: u64) -> bool {
+        !TFilter::matches_chunk(archetype, ticks, last_change_tick)
+    }
+}
+
+// T должен входить в кортеж доступа выборки, иначе world::query вернет None
+impl<T: 'static + TypeUuid> IQueryFilter for Added<T> {
+    fn matches(archetype: &Archetype) -> bool {
+        archetype::has::<T>(archetype)
+    }
+
+    fn is_archetypal() -> bool {
+        false
+    }
+
+    fn tick_uuids() -> Vec<Uuid> {
+        vec![Uuid::from_bytes(T::UUID)]
+    }
+
+    fn matches_chunk(archetype: &Archetype, ticks: &dyn Fn(Uuid) -> Option<ChunkTicks>, last_change_tick: u64) -> bool {
+        Self::matches(archetype) &&
+        ticks(Uuid::from_bytes(T::UUID)).is_some_and(|ticks| ticks.added > last_change_tick)
+    }
+}
+
+impl<T: 'static + TypeUuid> IQueryFilter for Changed<T> {
+    fn matches(archetype: &Archetype) -> bool {
+        archetype::has::<T>(archetype)
+    }
+
+    fn is_archetypal() -> bool {
+        false
+    }
+
+    fn tick_uuids() -> Vec<Uuid> {
+        vec![Uuid::from_bytes(T::UUID)]
+    }
+
+    fn matches_chunk(archetype: &Archetype, ticks: &dyn Fn(Uuid) -> Option<ChunkTicks>, last_change_tick: u64) -> bool {
+        Self::matches(archetype) &&
+        ticks(Uuid::from_bytes(T::UUID)).is_some_and(|ticks| ticks.changed > last_change_tick)
+    }
+}
+
+// кортеж фильтров работает как И, Or<(..)> - как ИЛИ
+macro_rules! impl_query_filter {
+    ($($filter:ident),+) => {
+        impl<$($filter: IQueryFilter),+> IQueryFilter for ($($filter,)+) {
+            fn matches(archetype: &Archetype) -> bool {
+                $($filter::matches(archetype))&&+
+            }
+
+            fn is_archetypal() -> bool {
+                $($filter::is_archetypal())&&+
+            }
+
+            fn tick_uuids() -> Vec<Uuid> {
+                [$($filter::tick_uuids(),)+].concat()
+            }
+
+            fn matches_chunk(archetype: &Archetype, ticks: &dyn Fn(Uuid) -> Option<ChunkTicks>, last_change_tick: u64) -> bool {
+                $($filter::matches_chunk(archetype, ticks, last_change_tick))&&+
+            }
+        }
+
+        impl<$($filter: IQueryFilter),+> IQueryFilter for Or<($($filter,)+)> {
+            fn matches(archetype: &Archetype) -> bool {
+                $($filter::matches(archetype))||+
+            }
+
+            fn is_archetypal() -> bool {
+                $($filter::is_archetypal())&&+
+            }
+
+            fn tick_uuids() -> Vec<Uuid> {
+                [$($filter::tick_uuids(),)+].concat()
+            }
+
+            fn matches_chunk(archetype: &Archetype, ticks: &dyn Fn(Uuid) -> Option<ChunkTicks>, last_change_tick: u64) -> bool {
+                $($filter::matches_chunk(archetype, ticks, last_change_tick))||+
+            }
+        }
+    };
+}
+
+impl_query_filter!(F1);
+impl_query_filter!(F1, F2);
+impl_query_filter!(F1, F2, F3);
+impl_query_filter!(F1, F2, F3, F4);
+impl_query_filter!(F1, F2, F3, F4, F5);
+impl_query_filter!(F1, F2, F3, F4, F5, F6);
+impl_query_filter!(F1, F2, F3, F4, F5, F6, F7);
+impl_query_filter!(F1, F2, F3, F4, F5, F6, F7, F8);
+impl_query_filter!(F1, F2, F3, F4, F5, F6, F7, F8, F9);
+impl_query_filter!(F1, F2, F3, F4, F5, F6, F7, F8, F9, F10);
+impl_query_filter!(F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11);
+impl_query_filter!(F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12);
+impl_query_filter!(F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13);
+impl_query_filter!(F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14);
+impl_query_filter!(F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15);
+impl_query_filter!(F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, F13, F14, F15, F16);
+
+// доступ к колонкам всех архетипов, в которых есть обязательные компоненты кортежа, колонки лежат в порядке номеров архетипов
+pub async fn get<'access, TAccessQuery>(world: &'access World) -> Option<TAccessQuery::TAccess<'access>>
+where
+    TAccessQuery: IAccessManager,
+{
+    let mut state = query_state::<TAccessQuery, ()>();
+
+    update_query_state(&mut state, world);
+
+    TAccessQuery::extract(world, &archetypes(world, &state.archetypes), next_change_tick(world)).await
+}
+
+// как get, но без ожидания: если хоть одна блокировка занята несовместимо, уже взятые отпускаются
+pub fn try_get<'access, TAccessQuery>(world: &'access World) -> Result<TAccessQuery::TAccess<'access>, AccessError>
+where
+    TAccessQuery: IAccessManager,
+{
+    let mut state = query_state::<TAccessQuery, ()>();
+
+    update_query_state(&mut state, world);
+
+    TAccessQuery::try_extract(world, &archetypes(world, &state.archetypes), next_change_tick(world))
+}
+
+// ждет блокировки, пока не завершится deadline - таймер рантайма, например tokio::time::sleep;
+// затем делает последнюю попытку без ожидания, и ошибка называет занятую колонку
+pub async fn get_until<'access, TAccessQuery>(world: &'access World, deadline: impl Future<Output = ()>) -> Result<TAccessQuery::TAccess<'access>, AccessError>
+where
+    TAccessQuery: IAccessManager,
+{
+    // недождавшийся get отпускает взятые блокировки при выходе из блока, до последней попытки
+    let access = {
+        let get = pin!(get::<TAccessQuery>(world));
+        let deadline = pin!(deadline);
+
+        match futures::future::select(get, deadline).await {
+            futures::future::Either::Left((access, _deadline)) => access,
+            futures::future::Either::Right(_) => None,
+        }
+    };
+
+    match access {
+        Some(access) => Ok(access),
+        None => try_get::<TAccessQuery>(world),
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub async fn get_timeout<'access, TAccessQuery>(world: &'access World, timeout: std::time::Duration) -> Result<TAccessQuery::TAccess<'access>, AccessError>
+where
+    TAccessQuery: IAccessManager,
+{
+    get_until::<TAccessQuery>(world, tokio::time::sleep(timeout)).await
+}
+
+pub trait IAccessManager {
+    type TAccess<'access>: 'access;
+    type TFetch<'fetch>;
+    type TItem<'fetch>;
+    type TRows<'fetch>: Iterator<Item = Self::TItem<'fetch>>;
+
+    // блокирует колонки только переданных архетипов; archetypes должны идти по возрастанию номеров
+    async fn extract<'access>(world: &'access World, archetypes: &[&'access Archetype], change_tick: u64) -> Option<Self::TAccess<'access>>;
+
+    // как extract, но без ожидания; порядок блокировок тот же, ошибка называет первую занятую
+    fn try_extract<'access>(world: &'access World, archetypes: &[&'access Archetype], change_tick: u64) -> Result<Self::TAccess<'access>, AccessError>;
+
+    fn type_uuids() -> Vec<Uuid>;
+
+    // компоненты, без которых архетип не попадает в выборку
+    fn required_uuids() -> Vec<Uuid>;
+
+    fn fetch<'fetch, 'access>(access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch>;
+
+    // строки чанка архетипа на указанной позиции, archetype_idx - индекс архетипа в списке, переданном в extract;
+    // len - число сущностей в чанке
+    fn rows<'fetch>(fetch: &mut Self::TFetch<'fetch>, archetype_idx: usize, archetype: &Archetype, chunk_position: usize, len: usize) -> Option<Self::TRows<'fetch>>;
+
+    fn ticks(fetch: &Self::TFetch<'_>, archetype_idx: usize, type_uuid: Uuid, chunk_id: usize) -> Option<ChunkTicks>;
+
+    fn component_access() -> ComponentAccess;
+}
+
+pub trait IAccessVariant {
+    type TAccess<'access>: 'access;
+    type TFetch<'fetch>;
+    type TItem<'fetch>;
+    type TRows<'fetch>: Iterator<Item = Self::TItem<'fetch>>;
+
+    // None если компонента нет в мире, а доступ к нему обязателен
+    async fn extract<'access>(world: &'access World, archetypes: &[&'access Archetype], change_tick: u64) -> Option<Self::TAccess<'access>>;
+
+    fn try_extract<'access>(world: &'access World, archetypes: &[&'access Archetype], change_tick: u64) -> Result<Self::TAccess<'access>, AccessError>;
+
+    fn type_uuid() -> Uuid;
+
+    fn type_info() -> TypeInfo;
+
+    fn is_optional() -> bool {
+        false
+    }
+
+    // ресурсы и каналы событий не хранятся в архетипах и не влияют на то, какие архетипы попадут в выборку
+    fn is_resource() -> bool {
+        false
+    }
+
+    fn fetch<'fetch, 'access>(access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch>;
+
+    fn rows<'fetch>(fetch: &mut Self::TFetch<'fetch>, archetype_idx: usize, chunk_id: Option<usize>, len: usize) -> Option<Self::TRows<'fetch>>;
+
+    fn ticks(fetch: &Self::TFetch<'_>, archetype_idx: usize, chunk_id: usize) -> Option<ChunkTicks>;
+
+    fn add_access(access: &mut ComponentAccess);
+}
+
+impl<T: 'static + Sync + Send + Debug> IAccessVariant for &mut T
+where T: TypeUuid
+{
+    // колонки архетипов выборки по порядку, None - у архетипа нет колонки
+    type TAccess<'access> = Vec<Option<WriteComponents<'access, T>>>;
+    // каждый чанк можно выдать на запись только один раз
+    type TFetch<'fetch> = Vec<Option<(u64, Vec<Option<&'fetch mut ComponentsChunk<T>>>)>>;
+    type TItem<'fetch> = &'fetch mut T;
+    type TRows<'fetch> = std::slice::IterMut<'fetch, T>;
+    
+    async fn extract<'access>(world: &'access World, archetypes: &[&'access Archetype], change_tick: u64) -> Option<Self::TAccess<'access>> {
+        // колонку EntityId выборка читает сама после блокировок доступа, запись в нее заблокировала бы выборку навсегда
+        if Self::type_uuid() == Uuid::from_bytes(EntityId::UUID) {
+            return None;
+        }
+
+        component_id(world, Self::type_uuid())?;
+
+        let mut columns = Vec::with_capacity(archetypes.len());
+
+        for archetype in archetypes {
+            columns.push(write_column::<T>(archetype, change_tick).await);
+        }
+
+        Some(columns)
+    }
+
+    fn try_extract<'access>(world: &'access World, archetypes: &[&'access Archetype], change_tick: u64) -> Result<Self::TAccess<'access>, AccessError> {
+        if Self::type_uuid() == Uuid::from_bytes(EntityId::UUID) {
+            return Err(AccessError::ReadOnly { component: TypeInfo::from_type::<T>() });
+        }
+
+        component_id(world, Self::type_uuid()).ok_or(AccessError::Missing { component: TypeInfo::from_type::<T>() })?;
+
+        archetypes.iter()
+            .map(|archetype| try_write_column::<T>(archetype, change_tick))
+            .collect()
+    }
+
+    fn type_uuid() -> Uuid {
+        Uuid::from_bytes(T::UUID)
+    }
+
+    fn type_info() -> TypeInfo {
+        TypeInfo::from_type::<T>()
+    }
+
+    fn fetch<'fetch, 'access>(access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch> {
+        access.iter_mut()
+            .map(|column| column.as_deref_mut().map(|column| {
+                let change_tick = component::change_tick(column);
+
+                let chunks = component::chunks_mut(column).iter_mut()
+                    .map(Some)
+                    .collect();
+
+                (change_tick, chunks)
+            }))
+            .collect()
+    }
+
+    fn rows<'fetch>(fetch: &mut Self::TFetch<'fetch>, archetype_idx: usize, chunk_id: Option<usize>, _len: usize) -> Option<Self::TRows<'fetch>> {
+        let (change_tick, chunks) = fetch.get_mut(archetype_idx)?.as_mut()?;
+        let chunk = chunks.get_mut(chunk_id?)?.take()?;
+
+        chunk::mark_changed(chunk, *change_tick);
+
+        Some(chunk::components_mut(chunk).iter_mut())
+    }
+
+    fn ticks(fetch: &Self::TFetch<'_>, archetype_idx: usize, chunk_id: usize) -> Option<ChunkTicks> {
+        let (_change_tick, chunks) = fetch.get(archetype_idx)?.as_ref()?;
+
+        chunks.get(chunk_id)?.as_deref().map(chunk::ticks)
+    }
+
+    fn add_access(access: &mut ComponentAccess) {
+        access.writes.insert(Self::type_uuid());
+    }
+}
+
+impl<T: 'static + Sync + Send + Debug> IAccessVariant for &T
+where T: TypeUuid
+{
+    type TAccess<'access> = Vec<Option<ReadComponents<'access, T>>>;
+    type TFetch<'fetch> = Vec<Option<&'fetch Components<T>>>;
+    type TItem<'fetch> = &'fetch T;
+    type TRows<'fetch> = std::slice::Iter<'fetch, T>;
+
+    async fn extract<'access>(world: &'access World, archetypes: &[&'access Archetype], _change_tick: u64) -> Option<Self::TAccess<'access>> {
+        component_id(world, Self::type_uuid())?;
+
+        let mut columns = Vec::with_capacity(archetypes.len());
+
+        for archetype in archetypes {
+            columns.push(read_column::<T>(archetype).await);
+        }
+
+        Some(columns)
+    }
+
+    fn try_extract<'access>(world: &'access World, archetypes: &[&'access Archetype], _change_tick: u64) -> Result<Self::TAccess<'access>, AccessError> {
+        component_id(world, Self::type_uuid()).ok_or(AccessError::Missing { component: TypeInfo::from_type::<T>() })?;
+
+        archetypes.iter()
+            .map(|archetype| try_read_column::<T>(archetype))
+            .collect()
+    }
+
+    fn type_uuid() -> Uuid {
+        Uuid::from_bytes(T::UUID)
+    }
+
+    fn type_info() -> TypeInfo {
+        TypeInfo::from_type::<T>()
+    }
+
+    fn fetch<'fetch, 'access>(access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch> {
+        access.iter()
+            .map(|column| column.as_deref())
+            .collect()
+    }
+
+    fn rows<'fetch>(fetch: &mut Self::TFetch<'fetch>, archetype_idx: usize, chunk_id: Option<usize>, _len: usize) -> Option<Self::TRows<'fetch>> {
+        let column = (*fetch.get(archetype_idx)?)?;
+        let chunk = component::chunk(column, chunk_id?)?;
+
+        Some(chunk::components(chunk).iter())
+    }
+
+    fn ticks(fetch: &Self::TFetch<'_>, archetype_idx: usize, chunk_id: usize) -> Option<ChunkTicks> {
+        component::chunk((*fetch.get(archetype_idx)?)?, chunk_id).map(chunk::ticks)
+    }
+
+    fn add_access(access: &mut ComponentAccess) {
+        access.reads.insert(Self::type_uuid());
+    }
+}
+
+// Option<&T> и Option<&mut T>: отсутствующая колонка дает None для каждой сущности вместо провала всей выборки
+impl<TVariant: IAccessVariant> IAccessVariant for Option<TVariant> {
+    type TAccess<'access> = Option<TVariant::TAccess<'access>>;
+    type TFetch<'fetch> = Option<TVariant::TFetch<'fetch>>;
+    type TItem<'fetch> = Option<TVariant::TItem<'fetch>>;
+    type TRows<'fetch> = OptionalRows<TVariant::TRows<'fetch>>;
+
+    async fn extract<'access>(world: &'access World, archetypes: &[&'access Archetype], change_tick: u64) -> Option<Self::TAccess<'access>> {
+        Some(TVariant::extract(world, archetypes, change_tick).await)
+    }
+
+    // отсутствие не ошибка, а занятая колонка - ошибка, как и у обязательного доступа;
+    // недоступный на запись компонент дает None, как и в extract
+    fn try_extract<'access>(world: &'access World, archetypes: &[&'access Archetype], change_tick: u64) -> Result<Self::TAccess<'access>, AccessError> {
+        match TVariant::try_extract(world, archetypes, change_tick) {
+            Ok(access) => Ok(Some(access)),
+            Err(AccessError::Missing { .. } | AccessError::ReadOnly { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn type_uuid() -> Uuid {
+        TVariant::type_uuid()
+    }
+
+    fn type_info() -> TypeInfo {
+        TVariant::type_info()
+    }
+
+    fn is_optional() -> bool {
+        true
+    }
+
+    fn is_resource() -> bool {
+        TVariant::is_resource()
+    }
+
+    fn fetch<'fetch, 'access>(access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch> {
+        access.as_mut().map(TVariant::fetch)
+    }
+
+    // у ресурсов и каналов событий нет чанков в архетипе: они есть у каждой строки, если вообще есть в мире
+    fn rows<'fetch>(fetch: &mut Self::TFetch<'fetch>, archetype_idx: usize, chunk_id: Option<usize>, len: usize) -> Option<Self::TRows<'fetch>> {
+        match fetch {
+            Some(fetch) if chunk_id.is_some() || TVariant::is_resource() => Some(OptionalRows::Present(TVariant::rows(fetch, archetype_idx, chunk_id, len)?)),
+            _ => Some(OptionalRows::Missing(len)),
+        }
+    }
+
+    fn ticks(fetch: &Self::TFetch<'_>, archetype_idx: usize, chunk_id: usize) -> Option<ChunkTicks> {
+        TVariant::ticks(fetch.as_ref()?, archetype_idx, chunk_id)
+    }
+
+    fn add_access(access: &mut ComponentAccess) {
+        TVariant::add_access(access);
+    }
+}
+
+// набор компонентов одной сущности для spawn_batch, колонки пачки собираются в типизированные Vec
+pub trait IBundle: Sized {
+    type TColumns: Send;
+
+    fn component_uuids() -> Vec<Uuid>;
+
+    fn component_sizes() -> Vec<usize>;
+
+    fn columns(capacity: usize) -> Self::TColumns;
+
+    fn push(bundle: Self, columns: &mut Self::TColumns);
+
+    // Send, чтобы spawn_batch с обобщенным набором можно было вызывать из задач на других потоках
+    fn extend(world: &mut World, archetype_id: ArchetypeId, columns: Self::TColumns, change_tick: u64) -> impl Future<Output = ()> + Send;
+}
+
+macro_rules! impl_bundle {
+    ($($component:ident $idx:tt),+) => {
+        impl<$($component: 'static + Sync + Send + TypeUuid + Debug),+> IBundle for ($($component,)+) {
+            type TColumns = ($(Vec<$component>,)+);
+
+            fn component_uuids() -> Vec<Uuid> {
+                vec![$(Uuid::from_bytes($component::UUID),)+]
+            }
+
+            fn component_sizes() -> Vec<usize> {
+                vec![$(std::mem::size_of::<$component>(),)+]
+            }
+
+            fn columns(capacity: usize) -> Self::TColumns {
+                ($(Vec::<$component>::with_capacity(capacity),)+)
+            }
+
+            fn push(bundle: Self, columns: &mut Self::TColumns) {
+                $(columns.$idx.push(bundle.$idx);)+
+            }
+
+            fn extend(world: &mut World, archetype_id: ArchetypeId, columns: Self::TColumns, change_tick: u64) -> impl Future<Output = ()> + Send {
+                async move {
+                    $(extend_column(world, archetype_id, columns.$idx, change_tick).await;)+
+                }
+            }
+        }
+    };
+}
+
+impl_bundle!(T1 0);
+impl_bundle!(T1 0, T2 1);
+impl_bundle!(T1 0, T2 1, T3 2);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12, T14 13);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12, T14 13, T15 14);
+impl_bundle!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7, T9 8, T10 9, T11 10, T12 11, T13 12, T14 13, T15 14, T16 15);
+
+pub type WriteComponents<'access, T> = MappedWriteGuard<'access, Components<T>>;
+pub type ReadComponents<'access, T> = MappedReadGuard<'access, Components<T>>;
+
+// Res<R> в кортеже доступа: ресурс на чтение, каждая строка выборки получает ссылку на него
+pub struct Res<TResource>(PhantomData<TResource>);
+
+// ResMut<R> в кортеже доступа: ресурс на запись; через get выдается сам guard,
+// а в строках выборки элемент пустой, потому что одну &mut нельзя раздать всем строкам
+pub struct ResMut<TResource>(PhantomData<TResource>);
+
+impl<TResource: 'static + Sync + Send + Debug + TypeUuid> IAccessVariant for Res<TResource> {
+    type TAccess<'access> = ReadResource<'access, TResource>;
+    type TFetch<'fetch> = &'fetch TResource;
+    type TItem<'fetch> = &'fetch TResource;
+    type TRows<'fetch> = std::iter::RepeatN<&'fetch TResource>;
+
+    async fn extract<'access>(world: &'access World, _archetypes: &[&'access Archetype], _change_tick: u64) -> Option<Self::TAccess<'access>> {
+        resource::<TResource>(world).await
+    }
+
+    fn try_extract<'access>(world: &'access World, _archetypes: &[&'access Archetype], _change_tick: u64) -> Result<Self::TAccess<'access>, AccessError> {
+        try_resource::<TResource>(world)
+    }
+
+    fn type_uuid() -> Uuid {
+        Uuid::from_bytes(TResource::UUID)
+    }
+
+    fn type_info() -> TypeInfo {
+        TypeInfo::from_type::<TResource>()
+    }
+
+    fn is_resource() -> bool {
+        true
+    }
+
+    fn fetch<'fetch, 'access>(access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch> {
+        access
+    }
+
+    fn rows<'fetch>(fetch: &mut Self::TFetch<'fetch>, _archetype_idx: usize, _chunk_id: Option<usize>, len: usize) -> Option<Self::TRows<'fetch>> {
+        Some(std::iter::repeat_n(*fetch, len))
+    }
+
+    fn ticks(_fetch: &Self::TFetch<'_>, _archetype_idx: usize, _chunk_id: usize) -> Option<ChunkTicks> {
+        None
+    }
+
+    fn add_access(access: &mut ComponentAccess) {
+        access.reads.insert(Self::type_uuid());
+        access.resources.insert(Self::type_uuid());
+    }
+}
+
+impl<TResource: 'static + Sync + Send + Debug + TypeUuid> IAccessVariant for ResMut<TResource> {
+    type TAccess<'access> = WriteResource<'access, TResource>;
+    type TFetch<'fetch> = ();
+    type TItem<'fetch> = ();
+    type TRows<'fetch> = std::iter::RepeatN<()>;
+
+    async fn extract<'access>(world: &'access World, _archetypes: &[&'access Archetype], _change_tick: u64) -> Option<Self::TAccess<'access>> {
+        resource_mut::<TResource>(world).await
+    }
+
+    fn try_extract<'access>(world: &'access World, _archetypes: &[&'access Archetype], _change_tick: u64) -> Result<Self::TAccess<'access>, AccessError> {
+        try_resource_mut::<TResource>(world)
+    }
+
+    fn type_uuid() -> Uuid {
+        Uuid::from_bytes(TResource::UUID)
+    }
+
+    fn type_info() -> TypeInfo {
+        TypeInfo::from_type::<TResource>()
+    }
+
+    fn is_resource() -> bool {
+        true
+    }
+
+    fn fetch<'fetch, 'access>(_access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch> {}
+
+    fn rows<'fetch>(_fetch: &mut Self::TFetch<'fetch>, _archetype_idx: usize, _chunk_id: Option<usize>, len: usize) -> Option<Self::TRows<'fetch>> {
+        Some(std::iter::repeat_n((), len))
+    }
+
+    fn ticks(_fetch: &Self::TFetch<'_>, _archetype_idx: usize, _chunk_id: usize) -> Option<ChunkTicks> {
+        None
+    }
+
+    fn add_access(access: &mut ComponentAccess) {
+        access.writes.insert(Self::type_uuid());
+        access.resources.insert(Self::type_uuid());
+    }
+}
+
+pub type WriteResource<'access, TResource> = MappedWriteGuard<'access, TResource>;
+pub type ReadResource<'access, TResource> = MappedReadGuard<'access, TResource>;
+
+// EventReader<E> в кортеже доступа: канал на чтение, события читаются через event::read курсором, который хранит система
+pub struct EventReader<TEvent>(PhantomData<TEvent>);
+
+// EventWriter<E> в кортеже доступа: канал на запись, в строках выборки элемент пустой, как у ResMut
+pub struct EventWriter<TEvent>(PhantomData<TEvent>);
+
+impl<TEvent: 'static + Sync + Send + Debug + TypeUuid> IAccessVariant for EventReader<TEvent> {
+    type TAccess<'access> = ReadEvents<'access, TEvent>;
+    type TFetch<'fetch> = &'fetch Events<TEvent>;
+    type TItem<'fetch> = &'fetch Events<TEvent>;
+    type TRows<'fetch> = std::iter::RepeatN<&'fetch Events<TEvent>>;
+
+    async fn extract<'access>(world: &'access World, _archetypes: &[&'access Archetype], _change_tick: u64) -> Option<Self::TAccess<'access>> {
+        events::<TEvent>(world).await
+    }
+
+    fn try_extract<'access>(world: &'access World, _archetypes: &[&'access Archetype], _change_tick: u64) -> Result<Self::TAccess<'access>, AccessError> {
+        try_events::<TEvent>(world)
+    }
+
+    fn type_uuid() -> Uuid {
+        Uuid::from_bytes(TEvent::UUID)
+    }
+
+    fn type_info() -> TypeInfo {
+        TypeInfo::from_type::<TEvent>()
+    }
+
+    fn is_resource() -> bool {
+        true
+    }
+
+    fn fetch<'fetch, 'access>(access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch> {
+        access
+    }
+
+    fn rows<'fetch>(fetch: &mut Self::TFetch<'fetch>, _archetype_idx: usize, _chunk_id: Option<usize>, len: usize) -> Option<Self::TRows<'fetch>> {
+        Some(std::iter::repeat_n(*fetch, len))
+    }
+
+    fn ticks(_fetch: &Self::TFetch<'_>, _archetype_idx: usize, _chunk_id: usize) -> Option<ChunkTicks> {
+        None
+    }
+
+    fn add_access(access: &mut ComponentAccess) {
+        access.reads.insert(Self::type_uuid());
+        access.resources.insert(Self::type_uuid());
+    }
+}
+
+impl<TEvent: 'static + Sync + Send + Debug + TypeUuid> IAccessVariant for EventWriter<TEvent> {
+    type TAccess<'access> = WriteEvents<'access, TEvent>;
+    type TFetch<'fetch> = ();
+    type TItem<'fetch> = ();
+    type TRows<'fetch> = std::iter::RepeatN<()>;
+
+    async fn extract<'access>(world: &'access World, _archetypes: &[&'access Archetype], _change_tick: u64) -> Option<Self::TAccess<'access>> {
+        events_mut::<TEvent>(world).await
+    }
+
+    fn try_extract<'access>(world: &'access World, _archetypes: &[&'access Archetype], _change_tick: u64) -> Result<Self::TAccess<'access>, AccessError> {
+        try_events_mut::<TEvent>(world)
+    }
+
+    fn type_uuid() -> Uuid {
+        Uuid::from_bytes(TEvent::UUID)
+    }
+
+    fn type_info() -> TypeInfo {
+        TypeInfo::from_type::<TEvent>()
+    }
+
+    fn is_resource() -> bool {
+        true
+    }
+
+    fn fetch<'fetch, 'access>(_access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch> {}
+
+    fn rows<'fetch>(_fetch: &mut Self::TFetch<'fetch>, _archetype_idx: usize, _chunk_id: Option<usize>, len: usize) -> Option<Self::TRows<'fetch>> {
+        Some(std::iter::repeat_n((), len))
+    }
+
+    fn ticks(_fetch: &Self::TFetch<'_>, _archetype_idx: usize, _chunk_id: usize) -> Option<ChunkTicks> {
+        None
+    }
+
+    fn add_access(access: &mut ComponentAccess) {
+        access.writes.insert(Self::type_uuid());
+        access.resources.insert(Self::type_uuid());
+    }
+}
+
+pub type WriteEvents<'access, TEvent> = MappedWriteGuard<'access, Events<TEvent>>;
+pub type ReadEvents<'access, TEvent> = MappedReadGuard<'access, Events<TEvent>>;
+
+// пустой доступ: колонки не блокируются, выборка проходит по всем сущностям архетипов
+impl IAccessManager for () {
+    type TAccess<'access> = ();
+    type TFetch<'fetch> = ();
+    type TItem<'fetch> = ();
+    type TRows<'fetch> = std::iter::RepeatN<()>;
+
+    async fn extract<'access>(_world: &'access World, _archetypes: &[&'access Archetype], _change_tick: u64) -> Option<Self::TAccess<'access>> {
+        Some(())
+    }
+
+    fn try_extract<'access>(_world: &'access World, _archetypes: &[&'access Archetype], _change_tick: u64) -> Result<Self::TAccess<'access>, AccessError> {
+        Ok(())
+    }
+
+    fn type_uuids() -> Vec<Uuid> {
+        vec![]
+    }
+
+    fn required_uuids() -> Vec<Uuid> {
+        vec![]
+    }
+
+    fn fetch<'fetch, 'access>(_access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch> {}
+
+    fn rows<'fetch>(_fetch: &mut Self::TFetch<'fetch>, _archetype_idx: usize, _archetype: &Archetype, _chunk_position: usize, len: usize) -> Option<Self::TRows<'fetch>> {
+        Some(std::iter::repeat_n((), len))
+    }
+
+    fn ticks(_fetch: &Self::TFetch<'_>, _archetype_idx: usize, _type_uuid: Uuid, _chunk_id: usize) -> Option<ChunkTicks> {
+        None
+    }
+
+    fn component_access() -> ComponentAccess {
+        ComponentAccess::default()
+    }
+}
+
+macro_rules! impl_access_manager {
+    ($($variant:ident $components:ident $idx:tt),+) => {
+        impl<$($variant: IAccessVariant),+> IAccessManager for ($($variant,)+) {
+            type TAccess<'access> = ($($variant::TAccess<'access>,)+);
+            type TFetch<'fetch> = ($($variant::TFetch<'fetch>,)+);
+            type TItem<'fetch> = ($($variant::TItem<'fetch>,)+);
+            type TRows<'fetch> = ZipRows<($($variant::TRows<'fetch>,)+)>;
+
+            async fn extract<'world>(world: &'world World, archetypes: &[&'world Archetype], change_tick: u64) -> Option<Self::TAccess<'world>> {
+                let mut uuids = vec![$($variant::type_uuid(),)+];
+
+                // блокировки берутся в порядке (uuid, номер архетипа), чтобы параллельные системы не ловили дедлок:
+                // варианты идут по uuid, каждый вариант блокирует колонки своих архетипов по возрастанию номеров
+                debug_assert!(archetypes.windows(2).all(|x| archetype::id(x[0]) < archetype::id(x[1])));
+
+                uuids.sort();
+
+                // второй вариант с тем же типом не получил бы колонку, так что доступ не выдается сразу, без блокировок
+                if uuids.windows(2).any(|x| x[0] == x[1]) {
+                    return None;
+                }
+
+                $(let mut $components = None;)+
+
+                for uuid in uuids {
+                    $(
+                        if $variant::type_uuid() == uuid {
+                            $components = Some($variant::extract(world, archetypes, change_tick).await?);
+                            continue;
+                        }
+                    )+
+                }
+
+                Some(($($components?,)+))
+            }
+
+            fn try_extract<'world>(world: &'world World, archetypes: &[&'world Archetype], change_tick: u64) -> Result<Self::TAccess<'world>, AccessError> {
+                let mut uuids = vec![$($variant::type_uuid(),)+];
+
+                uuids.sort();
+
+                // повтор отвергается так же, как в extract, какие бы варианты ни повторялись
+                if let Some(uuid) = uuids.windows(2).find(|x| x[0] == x[1]).map(|x| x[0]) {
+                    $(
+                        if $variant::type_uuid() == uuid {
+                            return Err(AccessError::Duplicate { component: $variant::type_info() });
+                        }
+                    )+
+                }
+
+                $(let mut $components = None;)+
+
+                for uuid in uuids {
+                    $(
+                        if $variant::type_uuid() == uuid {
+                            $components = Some($variant::try_extract(world, archetypes, change_tick)?);
+                            continue;
+                        }
+                    )+
+                }
+
+                Ok(($($components.unwrap(),)+))
+            }
+
+            fn type_uuids() -> Vec<Uuid> {
+                vec![$($variant::type_uuid(),)+]
+            }
+
+            fn required_uuids() -> Vec<Uuid> {
+                [$(($variant::type_uuid(), $variant::is_optional() || $variant::is_resource()),)+].into_iter()
+                    .filter(|(_uuid, is_optional)| !is_optional)
+                    .map(|(uuid, _is_optional)| uuid)
+                    .collect()
+            }
+
+            fn fetch<'fetch, 'access>(access: &'fetch mut Self::TAccess<'access>) -> Self::TFetch<'fetch> {
+                ($($variant::fetch(&mut access.$idx),)+)
+            }
+
+            fn rows<'fetch>(fetch: &mut Self::TFetch<'fetch>, archetype_idx: usize, archetype: &Archetype, chunk_position: usize, len: usize) -> Option<Self::TRows<'fetch>> {
+                Some(ZipRows(($(
+                    $variant::rows(
+                        &mut fetch.$idx,
+                        archetype_idx,
+                        archetype::chunk_ids(archetype, $variant::type_uuid()).and_then(|chunk_ids| chunk_ids.get(chunk_position)).copied(),
+                        len,
+                    )?,
+                )+)))
+            }
+
+            fn ticks(fetch: &Self::TFetch<'_>, archetype_idx: usize, type_uuid: Uuid, chunk_id: usize) -> Option<ChunkTicks> {
+                $(
+                    if $variant::type_uuid() == type_uuid {
+                        if let Some(ticks) = $variant::ticks(&fetch.$idx, archetype_idx, chunk_id) {
+                            return Some(ticks);
+                        }
+                    }
+                )+
+
+                None
+            }
+
+            fn component_access() -> ComponentAccess {
+                let mut access = ComponentAccess::default();
+                $($variant::add_access(&mut access);)+
+                access.required.extend(Self::required_uuids());
+                access
+            }
+        }
+    };
+}
+
+impl_access_manager!(T1 components_t1 0);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5, T7 components_t7 6);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5, T7 components_t7 6, T8 components_t8 7);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5, T7 components_t7 6, T8 components_t8 7, T9 components_t9 8);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5, T7 components_t7 6, T8 components_t8 7, T9 components_t9 8, T10 components_t10 9);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5, T7 components_t7 6, T8 components_t8 7, T9 components_t9 8, T10 components_t10 9, T11 components_t11 10);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5, T7 components_t7 6, T8 components_t8 7, T9 components_t9 8, T10 components_t10 9, T11 components_t11 10, T12 components_t12 11);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5, T7 components_t7 6, T8 components_t8 7, T9 components_t9 8, T10 components_t10 9, T11 components_t11 10, T12 components_t12 11, T13 components_t13 12);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5, T7 components_t7 6, T8 components_t8 7, T9 components_t9 8, T10 components_t10 9, T11 components_t11 10, T12 components_t12 11, T13 components_t13 12, T14 components_t14 13);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5, T7 components_t7 6, T8 components_t8 7, T9 components_t9 8, T10 components_t10 9, T11 components_t11 10, T12 components_t12 11, T13 components_t13 12, T14 components_t14 13, T15 components_t15 14);
+impl_access_manager!(T1 components_t1 0, T2 components_t2 1, T3 components_t3 2, T4 components_t4 3, T5 components_t5 4, T6 components_t6 5, T7 components_t7 6, T8 components_t8 7, T9 components_t9 8, T10 components_t10 9, T11 components_t11 10, T12 components_t12 11, T13 components_t13 12, T14 components_t14 13, T15 components_t15 14, T16 components_t16 15);
+
+// синхронный фасад для кода вне асинхронного рантайма: инструменты, синхронные тесты.
+// внутри рантайма вызывать нельзя: поток встанет на ожидании блокировки, которую может держать задача этого же потока
+pub mod blocking {
+    use std::fmt::Debug;
+
+    use futures::executor::block_on;
+    use type_uuid::TypeUuid;
+
+    use crate::{entity::EntityId, unknown_component::IntoComponentsInfo};
+
+    use super::{World, Query, IAccessManager, IQueryFilter, ReadResource, WriteResource};
+
+    pub fn get<TAccessQuery: IAccessManager>(world: &World) -> Option<TAccessQuery::TAccess<'_>> {
+        block_on(super::get::<TAccessQuery>(world))
+    }
+
+    pub fn query<TAccessQuery: IAccessManager, TFilter: IQueryFilter>(world: &World) -> Option<Query<'_, TAccessQuery, TFilter>> {
+        block_on(super::query::<TAccessQuery, TFilter>(world))
+    }
+
+    pub fn add_entity(world: &mut World, components: impl IntoComponentsInfo) -> EntityId {
+        block_on(super::add_entity(world, components))
+    }
+
+    pub fn remove_entity(world: &mut World, entity_id: EntityId) -> bool {
+        block_on(super::remove_entity(world, entity_id))
+    }
+
+    pub fn insert_component<TComponent>(world: &mut World, entity_id: EntityId, component: TComponent) -> bool
+    where
+        TComponent: 'static + Sync + Send + TypeUuid + Debug,
+    {
+        block_on(super::insert_component(world, entity_id, component))
+    }
+
+    pub fn remove_component<TComponent>(world: &mut World, entity_id: EntityId) -> Option<TComponent>
+    where
+        TComponent: 'static + Sync + Send + TypeUuid + Debug,
+    {
+        block_on(super::remove_component::<TComponent>(world, entity_id))
+    }
+
+    pub fn insert_resource<TResource>(world: &mut World, resource: TResource) -> Option<TResource>
+    where
+        TResource: 'static + Sync + Send + Debug + TypeUuid,
+    {
+        block_on(super::insert_resource(world, resource))
+    }
+
+    pub fn resource<TResource>(world: &World) -> Option<ReadResource<'_, TResource>>
+    where
+        TResource: 'static + Sync + Send + Debug + TypeUuid,
+    {
+        block_on(super::resource::<TResource>(world))
+    }
+
+    pub fn resource_mut<TResource>(world: &World) -> Option<WriteResource<'_, TResource>>
+    where
+        TResource: 'static + Sync + Send + Debug + TypeUuid,
+    {
+        block_on(super::resource_mut::<TResource>(world))
+    }
+}